@@ -1,38 +1,79 @@
 mod logger;
 mod util;
 
-use std::{error::Error, io};
+use std::{error::Error, io, process};
 use sysinfo::SystemExt;
 use termion::raw::IntoRawMode;
 use tui::{backend::TermionBackend, Terminal};
 
 use crate::logger::Logger;
-use crate::util::{event, ui, App};
+use crate::util::{
+    cli::{self, Command},
+    event,
+    settings::Settings,
+    ui, App,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    // Settings are checked before the terminal switches to raw mode so
+    // that errors print normally.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let overrides = match cli::parse(&args) {
+        Ok(Command::Run(overrides)) => overrides,
+        Ok(Command::Help) => {
+            print!("{}", cli::usage());
+            return Ok(());
+        }
+        Ok(Command::Version) => {
+            println!("rstop {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Err(errors) => {
+            for error in errors {
+                eprintln!("rstop: {}", error);
+            }
+            eprintln!("Try 'rstop --help' for more information.");
+            process::exit(2);
+        }
+    };
+    let (settings, keymap, theme) = match Settings::load(&overrides) {
+        Ok(loaded) => loaded,
+        Err(errors) => {
+            for line in errors.lines() {
+                eprintln!("rstop: {}", line);
+            }
+            process::exit(2);
+        }
+    };
+
     let stdout = io::stdout().into_raw_mode()?;
     //let stdout = MouseTerminal::from(stdout);
     //let stdout = AlternateScreen::from(stdout);
     let backend = TermionBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new("rstop", 20);
+    let mut events = event::Events::with_config(event::Config {
+        tick_rate: settings.tick_rate(),
+        ..event::Config::default()
+    });
+    let mut app = App::new("rstop", settings, keymap, theme);
     let mut logger = Logger::init();
 
-    let mut events = event::Events::default();
-
     loop {
         let mut system = sysinfo::System::new_all();
         //static system: Box<sysinfo::SystemExt> = sysinfo::System::new_all();
         terminal.draw(|f| ui::draw(f, &mut app, &mut logger))?;
 
         match &events.next_event()? {
-            event::Event::Input(key) => events.on_key(&key, &mut app, &mut system),
-            event::Event::Tick => {
-                system.refresh_all();
-                app.refresh(&system, &mut logger).await;
-            }
+            event::Event::Input(key) => events.on_key(&key, &mut app),
+            event::Event::Tick => app.refresh_now = true,
+        }
+
+        if app.refresh_now {
+            app.refresh_now = false;
+            system.refresh_all();
+            app.refresh(&system, &mut logger).await;
         }
 
         if app.should_quit {
@@ -0,0 +1,68 @@
+use std::io;
+use std::mem;
+
+/// Number of CPUs the kernel knows about, online or not.
+pub fn cpu_count() -> usize {
+    let n = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) };
+    if n < 1 {
+        1
+    } else {
+        n as usize
+    }
+}
+
+/// The CPUs `pid` is allowed to run on, from `sched_getaffinity(2)`.
+pub fn get_affinity(pid: sysinfo::Pid) -> io::Result<Vec<usize>> {
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    let ret = unsafe {
+        libc::sched_getaffinity(
+            pid as libc::pid_t,
+            mem::size_of::<libc::cpu_set_t>(),
+            &mut set,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok((0..libc::CPU_SETSIZE as usize)
+        .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, &set) })
+        .collect())
+}
+
+pub fn set_affinity(pid: sysinfo::Pid, cpus: &[usize]) -> io::Result<()> {
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    for &cpu in cpus {
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+
+    let ret = unsafe {
+        libc::sched_setaffinity(pid as libc::pid_t, mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if ret != 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Formats a sorted CPU list the way `taskset -c` does, e.g. `0-3,6`.
+pub fn format_cpu_list(cpus: &[usize]) -> String {
+    let mut ranges: Vec<String> = vec![];
+    let mut i = 0;
+    while i < cpus.len() {
+        let start = cpus[i];
+        let mut end = start;
+        while i + 1 < cpus.len() && cpus[i + 1] == end + 1 {
+            i += 1;
+            end = cpus[i];
+        }
+        ranges.push(if start == end {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, end)
+        });
+        i += 1;
+    }
+    ranges.join(",")
+}
@@ -1,23 +1,109 @@
 #[warn(dead_code)]
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
 use std::iter::Iterator;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::logger::Logger;
+use crate::util::{
+    affinity,
+    battery::{self, Battery},
+    columns::{self, Column},
+    deleted::{Marker, MarkerCache, StaleList},
+    detail::{self, Detail},
+    diskio::IoCounters,
+    files::{self, OpenFiles},
+    group,
+    keymap::Keymap,
+    lifecycle::Lifecycle,
+    pins::{self, Pin},
+    priority::{self, IoPriority},
+    procfs,
+    query::{self, Query},
+    search::Search,
+    settings::{Settings, Widget},
+    signal,
+    theme::Theme,
+    threads::{ThreadMode, ThreadTicks},
+    tree,
+    units::{self, Temperature},
+    users,
+};
 use futures::join;
 use queue::Queue;
-use sysinfo::{DiskExt, NetworkExt, ProcessExt, Processor, ProcessorExt, Signal, SystemExt};
+use sysinfo::{ComponentExt, DiskExt, NetworkExt, ProcessExt, ProcessorExt, SystemExt};
 
 #[derive(Debug)]
 pub struct App {
     pub name: String,
     pub temps: Vec<Vec<String>>,
+    pub batteries: Vec<Battery>,
     pub disk_usage: Vec<Vec<String>>,
     pub should_quit: bool,
+    /// Set by actions whose result only shows up after the next refresh;
+    /// the main loop refreshes right away instead of waiting for a tick.
+    pub refresh_now: bool,
     pub cpu_usage_queue: Vec<Queue<(f64, f64)>>,
     pub cpu_usage_points: Vec<Vec<(f64, f64)>>,
     pub max_capacity_queue: usize,
     pub memory: Memory,
     pub network: Network,
     pub process: Process,
+    pub input_mode: InputMode,
+    pub prompt: String,
+    pub status: Option<String>,
+    pub users: HashMap<u32, String>,
+    pub signal_index: usize,
+    pub signal_targets: Vec<(sysinfo::Pid, String)>,
+    pub affinity: Vec<bool>,
+    pub affinity_cursor: usize,
+    pub affinity_targets: Vec<(sysinfo::Pid, String)>,
+    pub column_cursor: usize,
+    pub detail: Option<Detail>,
+    pub open_files: Option<OpenFiles>,
+    pub stale: Option<StaleList>,
+    pub settings: Settings,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub help_cursor: usize,
+}
+
+/// Where key presses are routed: the normal bindings, or a prompt that is
+/// currently being typed into.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InputMode {
+    Normal,
+    Search,
+    Filter,
+    SignalPicker,
+    ConfirmSignal,
+    Renice,
+    IoPriority,
+    Affinity,
+    Columns,
+    Detail,
+    Files,
+    Events,
+    PinPattern,
+    Deleted,
+    OomAdj,
+    Help,
+}
+
+impl InputMode {
+    /// Label for the text prompts typed on the status line.
+    pub fn prompt_label(self) -> Option<&'static str> {
+        match self {
+            InputMode::Filter => Some("filter: "),
+            InputMode::Renice => Some("nice (-20..19): "),
+            InputMode::IoPriority => Some("io priority (rt/0-7, be/0-7, idle): "),
+            InputMode::PinPattern => Some("pin name or command containing: "),
+            InputMode::OomAdj => Some("oom_score_adj (-1000..1000): "),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -36,15 +122,239 @@ pub struct Network {
 
 #[derive(Debug)]
 pub struct Process {
-    pub process_list: Vec<(sysinfo::Pid, String, f32, u64)>,
+    pub process_list: Vec<ProcessInfo>,
+    pub rows: Vec<ProcessRow>,
     pub active_index: usize,
+    pub selected_pid: Option<sysinfo::Pid>,
     pub sort_by: SortBy,
+    pub sort_order: SortOrder,
+    pub tree_view: bool,
+    pub collapsed: HashSet<sysinfo::Pid>,
+    pub group_by: GroupBy,
+    pub expanded_groups: HashSet<String>,
+    pub selected_group: Option<String>,
+    pub tagged: HashSet<sysinfo::Pid>,
+    pub columns: Vec<Column>,
+    pub search: Search,
+    pub query: Option<Query>,
+    pub saved_filters: BTreeMap<String, String>,
+    pub threads: ThreadMode,
+    pub thread_ticks: ThreadTicks,
+    pub io_counters: IoCounters,
+    pub deleted_markers: MarkerCache,
+    pub lifecycle: Lifecycle,
+    pub pins: Vec<Pin>,
+}
+
+impl Process {
+    pub fn is_pinned(&self, process: &ProcessInfo) -> bool {
+        self.pins.iter().any(|pin| pin.matches(process))
+    }
+
+    /// The chosen columns, plus state and last CPU while listing threads.
+    pub fn visible_columns(&self) -> Vec<Column> {
+        let mut columns = self.columns.clone();
+        if self.threads == ThreadMode::Off {
+            return columns;
+        }
+
+        let mut at = columns
+            .iter()
+            .position(|c| *c == Column::Name)
+            .map_or(columns.len(), |i| i + 1);
+        for extra in [Column::State, Column::LastCpu].iter() {
+            if !columns.contains(extra) {
+                columns.insert(at, *extra);
+                at += 1;
+            }
+        }
+        columns
+    }
+
+    /// Whether a process passes both the `/` search and the filter query.
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        self.search.matches(process) && self.query.as_ref().map_or(true, |q| q.matches(process))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProcessInfo {
+    pub pid: sysinfo::Pid,
+    pub parent: Option<sysinfo::Pid>,
+    pub name: String,
+    pub cmd: String,
+    pub user: String,
+    pub state: char,
+    pub cpu: f32,
+    /// Resident memory in bytes.
+    pub mem: u64,
+    /// Disk bytes per second since the previous refresh.
+    pub read_rate: u64,
+    pub write_rate: u64,
+    /// Disk bytes since the process started.
+    pub read_total: u64,
+    pub write_total: u64,
+    pub nice: i32,
+    pub ioprio: Option<IoPriority>,
+    /// Allowed CPU list; only filled in while the CPUs column is shown.
+    pub cpus: String,
+    /// Open descriptors; only counted while the FDs column is shown.
+    pub fds: Option<usize>,
+    /// Whether the process runs a deleted binary or library; only checked
+    /// while the marker column is shown, and then at most every 30 seconds.
+    pub deleted: Option<Marker>,
+    /// The kernel's OOM badness and the user adjustment to it; only read
+    /// while an OOM column is shown or sorted on.
+    pub oom_score: Option<u32>,
+    pub oom_adj: Option<i32>,
+    /// CPU the process (or thread) last ran on.
+    pub last_cpu: Option<u32>,
+    /// Proportional set size in bytes; only read while the PSS column is
+    /// shown or sorted on.
+    pub pss: Option<u64>,
+    /// Cgroup path; only read while grouping by cgroup.
+    pub cgroup: Option<String>,
+}
+
+/// A line of the process table. `process_list` holds every process; `rows`
+/// is what is actually drawn once the current view has been applied.
+#[derive(Clone, Debug)]
+pub struct ProcessRow {
+    pub info: ProcessInfo,
+    pub prefix: String,
+    pub collapsed: bool,
+    pub has_children: bool,
+    pub matched: bool,
+    /// Set on group header rows, together with the PIDs folded into them.
+    pub group: Option<String>,
+    pub members: Vec<sysinfo::Pid>,
+    /// Set on rows of processes that have exited. They stay on screen for
+    /// a while but nothing acts on them, as the PID may be reused.
+    pub exited: bool,
+}
+
+impl ProcessRow {
+    pub fn plain(info: ProcessInfo) -> ProcessRow {
+        ProcessRow {
+            info,
+            prefix: String::new(),
+            collapsed: false,
+            has_children: false,
+            matched: true,
+            group: None,
+            members: vec![],
+            exited: false,
+        }
+    }
+
+    /// The processes an action on this row applies to.
+    pub fn pids(&self) -> Vec<sysinfo::Pid> {
+        if self.exited {
+            vec![]
+        } else if self.group.is_some() {
+            self.members.clone()
+        } else {
+            vec![self.info.pid]
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone)]
 pub enum SortBy {
+    PID,
+    NAME,
     CPU,
     MEMORY,
+    PSS,
+    READ,
+    WRITE,
+    IO,
+    OOM,
+    OOMADJ,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GroupBy {
+    Off,
+    Name,
+    User,
+    Cgroup,
+}
+
+impl GroupBy {
+    pub fn next(self) -> GroupBy {
+        match self {
+            GroupBy::Off => GroupBy::Name,
+            GroupBy::Name => GroupBy::User,
+            GroupBy::User => GroupBy::Cgroup,
+            GroupBy::Cgroup => GroupBy::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupBy::Off => "",
+            GroupBy::Name => "name",
+            GroupBy::User => "user",
+            GroupBy::Cgroup => "cgroup",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortBy {
+    pub const ALL: [SortBy; 10] = [
+        SortBy::PID,
+        SortBy::NAME,
+        SortBy::CPU,
+        SortBy::MEMORY,
+        SortBy::PSS,
+        SortBy::READ,
+        SortBy::WRITE,
+        SortBy::IO,
+        SortBy::OOM,
+        SortBy::OOMADJ,
+    ];
+
+    /// Direction a column starts in when it is picked: busiest first for the
+    /// usage columns, natural order for identifiers.
+    pub fn default_order(&self) -> SortOrder {
+        match self {
+            SortBy::PID | SortBy::NAME => SortOrder::Ascending,
+            SortBy::CPU
+            | SortBy::MEMORY
+            | SortBy::PSS
+            | SortBy::READ
+            | SortBy::WRITE
+            | SortBy::IO
+            | SortBy::OOM
+            | SortBy::OOMADJ => SortOrder::Descending,
+        }
+    }
+
+    pub fn next(&self) -> SortBy {
+        let i = SortBy::ALL.iter().position(|s| s == self).unwrap_or(0);
+        SortBy::ALL[(i + 1) % SortBy::ALL.len()].clone()
+    }
+
+    pub fn prev(&self) -> SortBy {
+        let i = SortBy::ALL.iter().position(|s| s == self).unwrap_or(0);
+        SortBy::ALL[(i + SortBy::ALL.len() - 1) % SortBy::ALL.len()].clone()
+    }
+}
+
+impl SortOrder {
+    pub fn reversed(self) -> SortOrder {
+        match self {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        }
+    }
 }
 
 //trait QueueTypes: Copy {}
@@ -53,12 +363,16 @@ pub enum SortBy {
 //impl QueueTypes for u64 {}
 
 impl App {
-    pub fn new(name: &str, max_capacity_queue: usize) -> App {
+    pub fn new(name: &str, settings: Settings, keymap: Keymap, theme: Theme) -> App {
+        let max_capacity_queue = settings.history;
+        units::set_byte_units(settings.units.bytes);
         App {
             name: String::from(name),
             temps: vec![vec![]],
+            batteries: vec![],
             disk_usage: vec![vec![]],
             should_quit: false,
+            refresh_now: false,
             cpu_usage_queue: vec![Queue::with_capacity(max_capacity_queue); max_capacity_queue],
             cpu_usage_points: vec![vec![]; max_capacity_queue],
             max_capacity_queue,
@@ -74,9 +388,45 @@ impl App {
             },
             process: Process {
                 process_list: vec![],
+                rows: vec![],
                 active_index: 0,
+                selected_pid: None,
                 sort_by: SortBy::MEMORY,
+                sort_order: SortBy::MEMORY.default_order(),
+                tree_view: false,
+                collapsed: HashSet::new(),
+                group_by: GroupBy::Off,
+                expanded_groups: HashSet::new(),
+                selected_group: None,
+                tagged: HashSet::new(),
+                columns: columns::DEFAULT_COLUMNS.to_vec(),
+                search: Search::default(),
+                query: None,
+                saved_filters: query::load_saved_filters(),
+                threads: ThreadMode::Off,
+                thread_ticks: ThreadTicks::default(),
+                io_counters: IoCounters::default(),
+                deleted_markers: MarkerCache::default(),
+                lifecycle: Lifecycle::default(),
+                pins: pins::load_pins(),
             },
+            input_mode: InputMode::Normal,
+            prompt: String::new(),
+            status: None,
+            users: users::read_passwd(),
+            signal_index: 0,
+            signal_targets: vec![],
+            affinity: vec![],
+            affinity_cursor: 0,
+            affinity_targets: vec![],
+            column_cursor: 0,
+            detail: None,
+            open_files: None,
+            stale: None,
+            keymap,
+            theme,
+            settings,
+            help_cursor: 0,
         }
     }
 
@@ -90,7 +440,8 @@ impl App {
         //new_arr.clone_from_slice(temp_data[..]);
 
         //temp_future
-        let temps_future = set_temp_section(system.get_components());
+        let temps_future =
+            set_temp_section(system.get_components(), self.settings.units.temperature);
         //self.temps = temps;
 
         //disk_usage_future
@@ -99,16 +450,44 @@ impl App {
 
         //cpu_usage_future
         //let cpu_usage_future = set_cpu_section(self, system.get_processors(), logger);
-        for (i, cpu_no) in system.get_processors().iter().enumerate() {
-            self.calculate_new_queue_processor(cpu_no, logger, i);
+        let processors = system.get_processors();
+        let usages: Vec<f64> = if self.settings.average_cpu {
+            let total: f64 = processors.iter().map(|p| p.get_cpu_usage() as f64).sum();
+            vec![total / processors.len().max(1) as f64]
+        } else {
+            processors
+                .iter()
+                .map(|p| p.get_cpu_usage() as f64)
+                .collect()
+        };
+        self.cpu_usage_queue
+            .resize(usages.len(), Queue::with_capacity(self.max_capacity_queue));
+        self.cpu_usage_points.resize(usages.len(), vec![]);
+        for (i, usage) in usages.into_iter().enumerate() {
+            self.calculate_new_queue_processor(usage, logger, i);
+        }
+
+        if self.settings.shows(Widget::Battery) {
+            self.batteries = battery::read_batteries();
         }
 
         //network_future
         let network_future = set_network_section(system.get_networks());
 
         //Setting process usage section
-        let process_future =
-            set_process_section(system.get_processes(), self.process.sort_by.clone());
+        let columns = self.process.visible_columns();
+        let process_future = set_process_section(
+            system.get_processes(),
+            &self.users,
+            &columns,
+            self.process.sort_by.clone(),
+            self.process.sort_order,
+            self.process.group_by,
+            self.process.threads,
+            &mut self.process.thread_ticks,
+            &mut self.process.io_counters,
+            &mut self.process.deleted_markers,
+        );
         //self.process.process_list = vec![];
 
         //Setting disk usage section data
@@ -132,7 +511,17 @@ impl App {
         self.temps = futures_resp.0;
         self.disk_usage = futures_resp.1;
         let network_values = futures_resp.2;
-        self.process.process_list = futures_resp.3;
+        let previous = std::mem::replace(&mut self.process.process_list, futures_resp.3);
+        self.process
+            .lifecycle
+            .update(&previous, &self.process.process_list);
+        self.prune_stale_pids();
+        self.reattach_pins();
+        self.rebuild_rows();
+        self.update_detail();
+        if let Some(open_files) = self.open_files.as_mut() {
+            open_files.update();
+        }
         self.network.rx_queue.force_queue(network_values.0);
         self.network.tx_queue.force_queue(network_values.1);
 
@@ -199,15 +588,13 @@ impl App {
 
     fn calculate_new_queue_processor(
         &mut self,
-        cpu_no: &Processor,
+        current_usage: f64,
         _logger: &mut Logger,
         i: usize,
     ) {
         let mut log: String = String::from("");
         let mut q: Queue<(f64, f64)> = Queue::with_capacity(self.max_capacity_queue);
 
-        let current_usage: f64 = cpu_no.get_cpu_usage() as f64;
-
         if self.cpu_usage_points[i].len() < self.max_capacity_queue {
             let l = self.cpu_usage_points[i].len();
 
@@ -253,56 +640,1119 @@ impl App {
         self.should_quit = true;
     }
 
-    pub fn decrease_index(&mut self) {
-        if self.process.active_index > 1 {
-            self.process.active_index -= 1;
+    pub fn decrease_index(&mut self, n: usize) {
+        if self.process.active_index > 0 {
+            self.select_index(self.process.active_index.saturating_sub(n));
         }
     }
 
-    pub fn increase_index(&mut self) {
-        if self.process.active_index < self.process.process_list.len() {
-            self.process.active_index += 1;
+    pub fn increase_index(&mut self, n: usize) {
+        let last = self.process.rows.len().saturating_sub(1);
+        if self.process.active_index < last {
+            self.select_index((self.process.active_index + n).min(last));
         }
     }
 
-    pub fn kill<T: SystemExt>(&mut self, system: &mut T) {
-        if let Some(process) =
-            system.get_process(self.process.process_list[self.process.active_index].0)
-        {
-            process.kill(Signal::Kill);
+    fn select_index(&mut self, index: usize) {
+        let row = self.process.rows.get(index);
+        self.process.active_index = index;
+        self.process.selected_pid = row.map(|r| r.info.pid);
+        self.process.selected_group = row.and_then(|r| r.group.clone());
+    }
+
+    pub fn selected_process(&self) -> Option<&ProcessInfo> {
+        self.process
+            .rows
+            .get(self.process.active_index)
+            .filter(|r| !r.exited)
+            .map(|r| &r.info)
+    }
+
+    /// Moves the cursor back onto the previously selected PID after the rows
+    /// have been rebuilt or re-sorted, clamping it if that process is gone.
+    fn restore_selection(&mut self) {
+        let rows = &self.process.rows;
+        let index = match &self.process.selected_group {
+            Some(group) => rows.iter().position(|r| r.group.as_ref() == Some(group)),
+            None => self.process.selected_pid.and_then(|pid| {
+                rows.iter()
+                    .position(|r| r.group.is_none() && r.info.pid == pid)
+            }),
         }
+        .unwrap_or_else(|| self.process.active_index.min(rows.len().saturating_sub(1)));
+        self.select_index(index);
     }
-}
 
-async fn set_temp_section(data: &[sysinfo::Component]) -> Vec<Vec<String>> {
-    //Setting the Temperatures section data
-    let temps: Vec<Vec<String>> =
-            //system
-            //.get_components()
-            data
+    /// Recomputes the visible rows from `process_list` for the current view.
+    /// With a search active, non-matching processes are dropped; the tree
+    /// view keeps their ancestors so matches stay in context.
+    pub fn rebuild_rows(&mut self) {
+        let process = &self.process;
+
+        // Pinned processes stay visible through filters and sit on top of
+        // every view as plain rows, in sort order; the view is built from
+        // the rest.
+        let (pinned, list): (Vec<&ProcessInfo>, Vec<&ProcessInfo>) = process
+            .process_list
             .iter()
-            .map(|x| {
-                let s: String = format!("{:?}", x);
-                let (mut s1, mut s2): (String, String) = (String::from("k"), String::from(""));
-                let mut flag = 0;
-
-                for i in s.chars() {
-                    if i == ':' {
-                        flag = 1;
-                        continue;
-                    }
+            .partition(|p| process.is_pinned(p));
+        let mut rows: Vec<ProcessRow> = pinned
+            .into_iter()
+            .map(|info| ProcessRow {
+                matched: process.matches(info),
+                ..ProcessRow::plain(info.clone())
+            })
+            .collect();
 
-                    if flag == 0 {
-                        s1.push(i);
-                    } else {
-                        s2.push(i);
+        if process.group_by != GroupBy::Off {
+            let visible: Vec<ProcessInfo> = list
+                .into_iter()
+                .filter(|p| process.matches(p))
+                .cloned()
+                .collect();
+            rows.extend(group::build_group_rows(
+                &visible,
+                process.group_by,
+                &process.expanded_groups,
+                &process.sort_by,
+                process.sort_order,
+            ));
+        } else if process.tree_view {
+            let filtering = process.search.is_active() || process.query.is_some();
+            let visible = if filtering {
+                let by_pid: HashMap<sysinfo::Pid, &ProcessInfo> =
+                    list.iter().map(|p| (p.pid, *p)).collect();
+                let mut keep = HashSet::new();
+                for matched in list.iter().filter(|p| process.matches(p)) {
+                    let mut current = Some(*matched);
+                    while let Some(p) = current {
+                        if !keep.insert(p.pid) {
+                            break;
+                        }
+                        current = p.parent.and_then(|parent| by_pid.get(&parent).copied());
                     }
                 }
-                vec![s1, s2]
+                list.into_iter()
+                    .filter(|p| keep.contains(&p.pid))
+                    .cloned()
+                    .collect()
+            } else {
+                list.into_iter().cloned().collect::<Vec<_>>()
+            };
+
+            let mut tree = tree::build_tree_rows(&visible, &process.collapsed);
+            for row in tree.iter_mut() {
+                row.matched = process.matches(&row.info);
+            }
+            rows.extend(tree);
+        } else {
+            rows.extend(
+                list.into_iter()
+                    .filter(|p| process.matches(p))
+                    .map(|info| ProcessRow::plain(info.clone())),
+            );
+        }
+
+        // Exited processes go last, in every view, and only for show.
+        let mut exited: Vec<ProcessInfo> = process
+            .lifecycle
+            .exited()
+            .filter(|p| process.matches(p))
+            .cloned()
+            .collect();
+        sort_process_list(&mut exited, &process.sort_by, process.sort_order);
+        rows.extend(exited.into_iter().map(|info| ProcessRow {
+            exited: true,
+            ..ProcessRow::plain(info)
+        }));
+        self.process.rows = rows;
+        self.restore_selection();
+    }
+
+    /// Forgets collapsed and tagged PIDs that no longer exist so a recycled
+    /// PID does not come back folded or tagged.
+    fn prune_stale_pids(&mut self) {
+        let alive: HashSet<sysinfo::Pid> =
+            self.process.process_list.iter().map(|p| p.pid).collect();
+        self.process.collapsed.retain(|pid| alive.contains(pid));
+        self.process.tagged.retain(|pid| alive.contains(pid));
+    }
+
+    pub fn sort_processes(&mut self) {
+        sort_process_list(
+            &mut self.process.process_list,
+            &self.process.sort_by,
+            self.process.sort_order,
+        );
+        self.rebuild_rows();
+    }
+
+    pub fn toggle_tree_view(&mut self) {
+        self.process.tree_view = !self.process.tree_view;
+        self.rebuild_rows();
+    }
+
+    /// Cycles processes → threads of the selected process → all threads.
+    pub fn toggle_threads(&mut self) {
+        self.process.threads = match self.process.threads {
+            ThreadMode::Off => match self.process.rows.get(self.process.active_index) {
+                Some(row) if row.group.is_none() && !row.exited => {
+                    ThreadMode::Process(row.info.pid)
+                }
+                _ => ThreadMode::All,
+            },
+            ThreadMode::Process(_) => ThreadMode::All,
+            ThreadMode::All => ThreadMode::Off,
+        };
+        self.process.lifecycle.rebase();
+        // The list still holds the old mode's rows; don't show them as if
+        // they were threads (or processes) while the new list is read. The
+        // selected PID is kept so the cursor lands back on it.
+        self.process.process_list.clear();
+        self.process.rows.clear();
+        self.refresh_now = true;
+    }
+
+    pub fn open_events(&mut self) {
+        self.process.lifecycle.cursor = 0;
+        self.input_mode = InputMode::Events;
+    }
+
+    pub fn events_scroll(&mut self, down: bool, n: usize) {
+        self.process.lifecycle.scroll(down, n);
+    }
+
+    pub fn open_help(&mut self) {
+        self.help_cursor = 0;
+        self.input_mode = InputMode::Help;
+    }
+
+    pub fn help_scroll(&mut self, down: bool, n: usize) {
+        let last = self.keymap.help().len().saturating_sub(1);
+        self.help_cursor = if down {
+            (self.help_cursor + n).min(last)
+        } else {
+            self.help_cursor.saturating_sub(n)
+        };
+    }
+
+    pub fn toggle_grouping(&mut self) {
+        self.process.group_by = self.process.group_by.next();
+        self.process.expanded_groups.clear();
+        self.process.selected_group = None;
+        if self.process.group_by == GroupBy::Cgroup {
+            // Later refreshes read them; until then fill in what is shown.
+            for process in self.process.process_list.iter_mut() {
+                process.cgroup = procfs::read_cgroup(process.pid);
+            }
+        }
+        self.rebuild_rows();
+    }
+
+    pub fn collapse_selected(&mut self) {
+        let row = match self.process.rows.get(self.process.active_index) {
+            Some(row) => row,
+            None => return,
+        };
+
+        if self.process.group_by != GroupBy::Off {
+            // Collapsing from a member row folds the group it belongs to.
+            let pid = row.info.pid;
+            let group = row.group.clone().or_else(|| {
+                self.process.rows[..self.process.active_index]
+                    .iter()
+                    .rev()
+                    .find(|r| r.group.is_some() && r.members.contains(&pid))
+                    .and_then(|r| r.group.clone())
+            });
+            if let Some(group) = group {
+                self.process.expanded_groups.remove(&group);
+                self.process.selected_group = Some(group);
+                self.rebuild_rows();
+            }
+            return;
+        }
+
+        if self.process.tree_view && row.has_children {
+            let pid = row.info.pid;
+            self.process.collapsed.insert(pid);
+            self.rebuild_rows();
+        }
+    }
+
+    pub fn expand_selected(&mut self) {
+        if let Some(row) = self.process.rows.get(self.process.active_index) {
+            if let Some(group) = &row.group {
+                if row.collapsed {
+                    let group = group.clone();
+                    self.process.expanded_groups.insert(group);
+                    self.rebuild_rows();
+                }
+            } else if self.process.tree_view && row.collapsed {
+                let pid = row.info.pid;
+                self.process.collapsed.remove(&pid);
+                self.rebuild_rows();
+            }
+        }
+    }
+
+    /// Sorts by `sort_by`; picking the column that is already active flips
+    /// the direction instead.
+    pub fn set_sort(&mut self, sort_by: SortBy) {
+        if self.process.sort_by == sort_by {
+            self.process.sort_order = self.process.sort_order.reversed();
+        } else {
+            self.process.sort_order = sort_by.default_order();
+            self.process.sort_by = sort_by;
+        }
+        self.sort_processes();
+    }
+
+    pub fn next_sort(&mut self, n: usize) {
+        self.set_sort(self.visible_sort(SortBy::next, n));
+    }
+
+    pub fn prev_sort(&mut self, n: usize) {
+        self.set_sort(self.visible_sort(SortBy::prev, n));
+    }
+
+    /// Steps `n` times through the sort keys, skipping ones whose column is
+    /// hidden. Whole laps around the shown keys are skipped.
+    fn visible_sort(&self, step: fn(&SortBy) -> SortBy, n: usize) -> SortBy {
+        let columns = self.process.visible_columns();
+        let shown = |sort_by: &SortBy| {
+            columns
+                .iter()
+                .any(|c| c.sort_by().as_ref() == Some(sort_by))
+        };
+        let laps = SortBy::ALL.iter().filter(|s| shown(s)).count().max(1);
+
+        let mut sort_by = self.process.sort_by.clone();
+        for _ in 0..(n.max(1) - 1) % laps + 1 {
+            sort_by = step(&sort_by);
+            for _ in 0..SortBy::ALL.len() {
+                if shown(&sort_by) {
+                    break;
+                }
+                sort_by = step(&sort_by);
+            }
+        }
+        sort_by
+    }
+
+    pub fn reverse_sort(&mut self) {
+        self.process.sort_order = self.process.sort_order.reversed();
+        self.sort_processes();
+    }
+
+    pub fn start_search(&mut self) {
+        self.input_mode = InputMode::Search;
+    }
+
+    /// Leaves the prompt, keeping the filter in place.
+    pub fn confirm_search(&mut self) {
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Leaves the prompt and drops the filter.
+    pub fn cancel_search(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.process.search.clear();
+        self.rebuild_rows();
+    }
+
+    pub fn search_push(&mut self, c: char) {
+        self.process.search.push(c);
+        self.rebuild_rows();
+    }
+
+    pub fn search_pop(&mut self) {
+        self.process.search.pop();
+        self.rebuild_rows();
+    }
+
+    pub fn search_toggle_regex(&mut self) {
+        self.process.search.toggle_regex();
+        self.rebuild_rows();
+    }
+
+    /// Opens the filter prompt with the active query ready for editing.
+    pub fn start_filter(&mut self) {
+        let prompt = self
+            .process
+            .query
+            .as_ref()
+            .map(|q| q.text.clone())
+            .unwrap_or_default();
+        // Unlike the other prompts this needs no target: a filter that
+        // hides every row must still be editable.
+        self.prompt = prompt;
+        self.status = None;
+        self.input_mode = InputMode::Filter;
+    }
+
+    /// Applies the filter prompt. Besides expressions it understands
+    /// `:save NAME` and `:rm NAME` for managing saved filters; an empty
+    /// prompt clears the filter.
+    fn confirm_filter(&mut self) {
+        let input = self.prompt.trim().to_string();
+
+        if let Some(name) = input.strip_prefix(":save ") {
+            let name = name.trim().trim_start_matches('@');
+            match &self.process.query {
+                Some(_) if !query::is_valid_name(name) => {
+                    self.status = Some(String::from(
+                        "filter names may only use letters, digits, '_' and '-'",
+                    ));
+                    return;
+                }
+                Some(q) => {
+                    self.process
+                        .saved_filters
+                        .insert(name.to_string(), q.text.clone());
+                    self.store_filters(format!("saved filter @{}", name));
+                }
+                _ => self.status = Some(String::from("no active filter to save")),
+            }
+        } else if let Some(name) = input.strip_prefix(":rm ") {
+            let name = name.trim().trim_start_matches('@');
+            if self.process.saved_filters.remove(name).is_some() {
+                self.store_filters(format!("removed filter @{}", name));
+            } else {
+                self.status = Some(format!("no saved filter named '{}'", name));
+            }
+        } else if input.is_empty() {
+            self.process.query = None;
+            self.status = None;
+        } else {
+            match Query::parse(&input, &self.process.saved_filters) {
+                Ok(q) => {
+                    self.process.query = Some(q);
+                    self.status = None;
+                }
+                Err(err) => {
+                    self.status = Some(format!("filter error: {}", err));
+                    return;
+                }
+            }
+        }
+
+        self.input_mode = InputMode::Normal;
+        self.rebuild_rows();
+    }
+
+    fn store_filters(&mut self, message: String) {
+        self.status = Some(
+            match query::store_saved_filters(&self.process.saved_filters) {
+                Ok(()) => message,
+                Err(err) => format!("could not save filters: {}", err),
+            },
+        );
+    }
+
+    pub fn prompt_push(&mut self, c: char) {
+        self.prompt.push(c);
+    }
+
+    pub fn prompt_pop(&mut self) {
+        self.prompt.pop();
+    }
+
+    /// Moves to the next (or previous) row matching the search, wrapping
+    /// around the table.
+    pub fn jump_to_match(&mut self, forward: bool, n: usize) {
+        let len = self.process.rows.len();
+        if len == 0 || !self.process.search.is_active() {
+            return;
+        }
+
+        let matches: Vec<usize> = (0..len).filter(|i| self.process.rows[*i].matched).collect();
+        if matches.is_empty() {
+            return;
+        }
+
+        // The nth match after (or before) the cursor, wrapping around.
+        let active = self.process.active_index;
+        let after = matches.iter().filter(|i| **i <= active).count();
+        let before = matches.iter().filter(|i| **i < active).count();
+        let m = matches.len();
+        let index = if forward {
+            matches[(after + n - 1) % m]
+        } else {
+            matches[(before + m * n - n) % m]
+        };
+        self.select_index(index);
+    }
+
+    /// PIDs the selected row stands for: every member of a group header,
+    /// otherwise just the selected process.
+    pub fn selected_pids(&self) -> Vec<sysinfo::Pid> {
+        self.process
+            .rows
+            .get(self.process.active_index)
+            .map(|r| r.pids())
+            .unwrap_or_default()
+    }
+
+    /// Tags or untags the selected row and moves down, like htop's space.
+    /// On a group header this flips every member at once.
+    pub fn toggle_tag(&mut self) {
+        let pids = self.selected_pids();
+        if pids.is_empty() {
+            return;
+        }
+
+        if pids.iter().all(|pid| self.process.tagged.contains(pid)) {
+            for pid in &pids {
+                self.process.tagged.remove(pid);
+            }
+        } else {
+            self.process.tagged.extend(pids);
+        }
+        self.increase_index(1);
+    }
+
+    /// Pins the selected process, or unpins it if any pin already covers it.
+    pub fn toggle_pin(&mut self) {
+        let info = match self.process.rows.get(self.process.active_index) {
+            Some(row) if row.group.is_none() && !row.exited => row.info.clone(),
+            _ => return,
+        };
+
+        let before = self.process.pins.len();
+        self.process.pins.retain(|pin| !pin.matches(&info));
+        let message = if self.process.pins.len() < before {
+            format!("unpinned {}", info.name)
+        } else {
+            self.process.pins.push(Pin::for_process(&info));
+            format!("pinned {}", info.name)
+        };
+        self.store_pins(message);
+        self.rebuild_rows();
+    }
+
+    pub fn start_pin_pattern(&mut self) {
+        self.prompt.clear();
+        self.status = None;
+        self.input_mode = InputMode::PinPattern;
+    }
+
+    fn confirm_pin_pattern(&mut self) {
+        self.input_mode = InputMode::Normal;
+        let pattern = self.prompt.trim().to_string();
+        if pattern.is_empty() {
+            return;
+        }
+
+        let pin = Pin::Pattern(pattern.clone());
+        if !self.process.pins.contains(&pin) {
+            self.process.pins.push(pin);
+        }
+        self.store_pins(format!("pinned processes matching '{}'", pattern));
+        self.rebuild_rows();
+    }
+
+    /// Follows PID pins to their program's new process after a restart.
+    /// Thread lists are skipped, as threads share their process's command
+    /// line.
+    fn reattach_pins(&mut self) {
+        if self.process.threads != ThreadMode::Off {
+            return;
+        }
+
+        let mut taken: HashSet<sysinfo::Pid> = self
+            .process
+            .pins
+            .iter()
+            .filter_map(|pin| match pin {
+                Pin::Pid(pid, _) => Some(*pid),
+                Pin::Pattern(_) => None,
+            })
+            .collect();
+        let mut changed = false;
+        for pin in self.process.pins.iter_mut() {
+            if pin.reattach(&self.process.process_list, &taken) {
+                if let Pin::Pid(pid, _) = pin {
+                    taken.insert(*pid);
+                }
+                changed = true;
+            }
+        }
+        if changed {
+            if let Err(err) = pins::store_pins(&self.process.pins) {
+                self.status = Some(format!("could not save pins: {}", err));
+            }
+        }
+    }
+
+    fn store_pins(&mut self, message: String) {
+        self.status = Some(match pins::store_pins(&self.process.pins) {
+            Ok(()) => message,
+            Err(err) => format!("could not save pins: {}", err),
+        });
+    }
+
+    /// Tags every process that passes the current search and filter.
+    pub fn tag_matching(&mut self) {
+        let process = &self.process;
+        let matching: Vec<sysinfo::Pid> = process
+            .process_list
+            .iter()
+            .filter(|p| process.matches(p))
+            .map(|p| p.pid)
+            .collect();
+        self.status = Some(format!("tagged {} processes", matching.len()));
+        self.process.tagged.extend(matching);
+    }
+
+    pub fn untag_all(&mut self) {
+        self.process.tagged.clear();
+    }
+
+    /// What batch actions operate on: the tagged processes if there are any,
+    /// otherwise the selected row.
+    pub fn action_targets(&self) -> Vec<sysinfo::Pid> {
+        if self.process.tagged.is_empty() {
+            return self.selected_pids();
+        }
+        self.process
+            .process_list
+            .iter()
+            .filter(|p| self.process.tagged.contains(&p.pid))
+            .map(|p| p.pid)
+            .collect()
+    }
+
+    /// Writes the action targets as tab-separated values to
+    /// `rstop-<unix time>.tsv` in the working directory.
+    pub fn export_targets(&mut self) {
+        let targets: HashSet<sysinfo::Pid> = self.action_targets().into_iter().collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let mut contents =
+            String::from("pid\tppid\tuser\tstate\tcpu\tmem\tread\twrite\tname\tcmd\n");
+        for p in self
+            .process
+            .process_list
+            .iter()
+            .filter(|p| targets.contains(&p.pid))
+        {
+            contents += &format!(
+                "{}\t{}\t{}\t{}\t{:.1}\t{}\t{}\t{}\t{}\t{}\n",
+                p.pid,
+                p.parent.map(|pid| pid.to_string()).unwrap_or_default(),
+                p.user,
+                p.state,
+                p.cpu,
+                p.mem,
+                p.read_total,
+                p.write_total,
+                p.name,
+                p.cmd.replace('\t', " ")
+            );
+        }
+
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = format!("rstop-{}.tsv", secs);
+        self.status = Some(match fs::write(&path, contents) {
+            Ok(()) => format!("exported {} processes to {}", targets.len(), path),
+            Err(err) => format!("could not write {}: {}", path, err),
+        });
+    }
+
+    /// Opens the signal picker for the tagged processes, or the selected row. The targets are
+    /// captured now so a refresh cannot change what the signal is sent to.
+    pub fn open_signal_picker(&mut self) {
+        let pids = self.action_targets();
+        if pids.is_empty() {
+            return;
+        }
+
+        self.signal_targets = self.with_names(pids);
+        self.signal_index = 0;
+        self.status = None;
+        self.input_mode = InputMode::SignalPicker;
+    }
+
+    pub fn signal_picker_up(&mut self, n: usize) {
+        self.signal_index = self.signal_index.saturating_sub(n);
+    }
+
+    pub fn signal_picker_down(&mut self, n: usize) {
+        let last = signal::SIGNALS.len().saturating_sub(1);
+        self.signal_index = (self.signal_index + n).min(last);
+    }
+
+    pub fn choose_signal(&mut self) {
+        self.input_mode = InputMode::ConfirmSignal;
+    }
+
+    pub fn cancel_signal(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.signal_targets.clear();
+    }
+
+    /// Sends the chosen signal to every target and reports the outcome on
+    /// the status line.
+    pub fn send_signal(&mut self) {
+        let (name, signum) = signal::SIGNALS[self.signal_index];
+        let targets = std::mem::take(&mut self.signal_targets);
+        self.input_mode = InputMode::Normal;
+
+        self.apply_to_targets(&targets, name, &format!("sent {} to", name), |pid| {
+            signal::send(pid, signum)
+        });
+    }
+
+    fn with_names(&self, pids: Vec<sysinfo::Pid>) -> Vec<(sysinfo::Pid, String)> {
+        pids.into_iter()
+            .map(|pid| {
+                let name = self
+                    .process
+                    .process_list
+                    .iter()
+                    .find(|p| p.pid == pid)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_default();
+                (pid, name)
             })
+            .collect()
+    }
+
+    /// Runs `action` on every target and puts a summary on the status line,
+    /// including the OS error for the first failure. `what` names the change
+    /// in failures ("SIGTERM failed for ...") and `done` reports it ("sent
+    /// SIGTERM to ...").
+    fn apply_to_targets<F>(
+        &mut self,
+        targets: &[(sysinfo::Pid, String)],
+        what: &str,
+        done: &str,
+        mut action: F,
+    ) where
+        F: FnMut(sysinfo::Pid) -> io::Result<()>,
+    {
+        if targets.is_empty() {
+            self.status = Some(String::from("no process selected"));
+            return;
+        }
+
+        let mut failures = vec![];
+        for (pid, name) in targets {
+            if let Err(err) = action(*pid) {
+                failures.push(format!("{} ({}): {}", pid, name, err));
+            }
+        }
+
+        self.status = Some(match (targets, failures.len()) {
+            ([(pid, name)], 0) => format!("{} {} ({})", done, pid, name),
+            (_, 0) => format!("{} {} processes", done, targets.len()),
+            ([_], _) => format!("{} failed for {}", what, failures[0]),
+            (_, n) => format!(
+                "{} {} of {} processes; failed for {}",
+                done,
+                targets.len() - n,
+                targets.len(),
+                failures[0]
+            ),
+        });
+        self.refresh_priorities();
+    }
+
+    /// Re-reads nice, I/O priority and OOM adjustment so the table reflects
+    /// a change right away instead of on the next tick.
+    fn refresh_priorities(&mut self) {
+        let columns = self.process.visible_columns();
+        let wants_ioprio = columns.contains(&Column::IoPriority);
+        let wants_nice = wants_ioprio || columns.contains(&Column::Nice);
+        for p in self.process.process_list.iter_mut() {
+            if wants_nice {
+                p.nice = current_nice(p.pid);
+            }
+            if wants_ioprio {
+                p.ioprio = priority::get_ioprio(p.pid);
+            }
+            if p.oom_adj.is_some() {
+                p.oom_score = procfs::read_number(p.pid, "oom_score");
+                p.oom_adj = procfs::read_number(p.pid, "oom_score_adj");
+            }
+        }
+        self.rebuild_rows();
+    }
+
+    pub fn start_renice(&mut self) {
+        let nice = self
+            .selected_process()
+            .map(|p| current_nice(p.pid))
+            .unwrap_or(0);
+        self.start_prompt(InputMode::Renice, nice.to_string());
+    }
+
+    pub fn start_ioprio(&mut self) {
+        let current = self
+            .selected_process()
+            .map(|p| priority::format_ioprio(priority::get_ioprio(p.pid), current_nice(p.pid)))
+            .unwrap_or_default();
+        self.start_prompt(InputMode::IoPriority, current);
+    }
+
+    pub fn start_oom_adj(&mut self) {
+        let adj = self
+            .selected_process()
+            .and_then(|p| {
+                p.oom_adj
+                    .or_else(|| procfs::read_number(p.pid, "oom_score_adj"))
+            })
+            .unwrap_or(0);
+        self.start_prompt(InputMode::OomAdj, adj.to_string());
+    }
+
+    fn confirm_oom_adj(&mut self) {
+        let adj = match self.prompt.trim().parse::<i32>() {
+            Ok(adj) if (-1000..=1000).contains(&adj) => adj,
+            _ => {
+                self.status = Some(String::from(
+                    "oom_score_adj must be a number from -1000 to 1000",
+                ));
+                return;
+            }
+        };
+
+        self.input_mode = InputMode::Normal;
+        let targets = self.with_names(self.action_targets());
+        self.apply_to_targets(
+            &targets,
+            &format!("oom_score_adj {}", adj),
+            &format!("set oom_score_adj {} on", adj),
+            |pid| priority::set_oom_score_adj(pid, adj),
+        );
+    }
+
+    fn start_prompt(&mut self, mode: InputMode, initial: String) {
+        if self.action_targets().is_empty() {
+            return;
+        }
+        self.prompt = initial;
+        self.status = None;
+        self.input_mode = mode;
+    }
+
+    fn confirm_renice(&mut self) {
+        let nice = match self.prompt.trim().parse::<i32>() {
+            Ok(nice) if (-20..=19).contains(&nice) => nice,
+            _ => {
+                self.status = Some(String::from("nice must be a number from -20 to 19"));
+                return;
+            }
+        };
+
+        self.input_mode = InputMode::Normal;
+        let targets = self.with_names(self.action_targets());
+        self.apply_to_targets(
+            &targets,
+            &format!("nice {}", nice),
+            &format!("set nice {} on", nice),
+            |pid| priority::set_nice(pid, nice),
+        );
+    }
+
+    /// Moves the targets' nice value by `delta`, like htop's F7/F8.
+    pub fn nudge_nice(&mut self, delta: i32) {
+        let targets = self.with_names(self.action_targets());
+        let (what, done) = if delta < 0 {
+            ("raising priority", "raised priority of")
+        } else {
+            ("lowering priority", "lowered priority of")
+        };
+        self.apply_to_targets(&targets, what, done, |pid| {
+            let nice = current_nice(pid) + delta;
+            priority::set_nice(pid, nice.max(-20).min(19))
+        });
+    }
+
+    fn confirm_ioprio(&mut self) {
+        let ioprio = match priority::IoPriority::parse(&self.prompt) {
+            Ok(ioprio) => ioprio,
+            Err(err) => {
+                self.status = Some(err);
+                return;
+            }
+        };
+
+        self.input_mode = InputMode::Normal;
+        let label = priority::format_ioprio(Some(ioprio), 0);
+        let targets = self.with_names(self.action_targets());
+        self.apply_to_targets(
+            &targets,
+            &format!("I/O priority {}", label),
+            &format!("set I/O priority {} on", label),
+            |pid| priority::set_ioprio(pid, ioprio),
+        );
+    }
+
+    /// Opens the affinity editor seeded with the selected process's mask.
+    pub fn open_affinity(&mut self) {
+        let targets = self.with_names(self.action_targets());
+        let (pid, name) = match targets.first() {
+            Some(target) => target.clone(),
+            None => return,
+        };
+
+        match affinity::get_affinity(pid) {
+            Ok(cpus) => {
+                let count = affinity::cpu_count().max(cpus.iter().max().map_or(0, |c| c + 1));
+                self.affinity = (0..count).map(|cpu| cpus.contains(&cpu)).collect();
+                self.affinity_cursor = 0;
+                self.affinity_targets = targets;
+                self.status = None;
+                self.input_mode = InputMode::Affinity;
+            }
+            Err(err) => {
+                self.status = Some(format!("affinity of {} ({}) failed: {}", pid, name, err));
+            }
+        }
+    }
+
+    pub fn affinity_up(&mut self, n: usize) {
+        self.affinity_cursor = self.affinity_cursor.saturating_sub(n);
+    }
+
+    pub fn affinity_down(&mut self, n: usize) {
+        let last = self.affinity.len().saturating_sub(1);
+        self.affinity_cursor = (self.affinity_cursor + n).min(last);
+    }
+
+    pub fn affinity_toggle(&mut self) {
+        if let Some(allowed) = self.affinity.get_mut(self.affinity_cursor) {
+            *allowed = !*allowed;
+        }
+    }
+
+    /// Selects every CPU, or clears them all if they already are.
+    pub fn affinity_toggle_all(&mut self) {
+        let all = self.affinity.iter().all(|a| *a);
+        for allowed in self.affinity.iter_mut() {
+            *allowed = !all;
+        }
+    }
+
+    pub fn confirm_affinity(&mut self) {
+        let cpus: Vec<usize> = self
+            .affinity
+            .iter()
+            .enumerate()
+            .filter(|(_, allowed)| **allowed)
+            .map(|(cpu, _)| cpu)
             .collect();
+        if cpus.is_empty() {
+            self.status = Some(String::from("select at least one CPU"));
+            return;
+        }
 
-    return temps;
+        self.input_mode = InputMode::Normal;
+        let targets = std::mem::take(&mut self.affinity_targets);
+        let list = affinity::format_cpu_list(&cpus);
+        self.apply_to_targets(
+            &targets,
+            &format!("affinity {}", list),
+            &format!("set affinity {} on", list),
+            |pid| affinity::set_affinity(pid, &cpus),
+        );
+    }
+
+    pub fn open_columns(&mut self) {
+        self.column_cursor = 0;
+        self.input_mode = InputMode::Columns;
+    }
+
+    pub fn columns_up(&mut self, n: usize) {
+        self.column_cursor = self.column_cursor.saturating_sub(n);
+    }
+
+    pub fn columns_down(&mut self, n: usize) {
+        let last = columns::ALL_COLUMNS.len().saturating_sub(1);
+        self.column_cursor = (self.column_cursor + n).min(last);
+    }
+
+    /// Shows or hides the column under the cursor, keeping the table in
+    /// `ALL_COLUMNS` order.
+    pub fn toggle_column(&mut self) {
+        let column = columns::ALL_COLUMNS[self.column_cursor];
+        if !column.optional() {
+            return;
+        }
+
+        let mut shown: Vec<Column> = self.process.columns.clone();
+        if shown.contains(&column) {
+            shown.retain(|c| *c != column);
+        } else {
+            shown.push(column);
+        }
+        self.process.columns = columns::ALL_COLUMNS
+            .iter()
+            .copied()
+            .filter(|c| shown.contains(c))
+            .collect();
+    }
+
+    /// Opens the detail pane for the selected process. Its charts start
+    /// empty and fill in on each refresh while the pane stays open.
+    pub fn open_detail(&mut self) {
+        let info = match self.process.rows.get(self.process.active_index) {
+            Some(row) if row.group.is_none() => row.info.clone(),
+            _ => return,
+        };
+
+        let mut detail = Detail::new(&info);
+        detail.update(&self.process.process_list, &self.users, None);
+        self.detail = Some(detail);
+        self.input_mode = InputMode::Detail;
+    }
+
+    pub fn close_detail(&mut self) {
+        self.detail = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    fn update_detail(&mut self) {
+        let detail = match self.detail.as_mut() {
+            Some(detail) => detail,
+            None => return,
+        };
+
+        let sample = self
+            .process
+            .process_list
+            .iter()
+            .find(|p| p.pid == detail.pid)
+            .map(|p| detail::Sample {
+                cpu: p.cpu,
+                mem: p.mem,
+                io: p.read_rate + p.write_rate,
+            });
+        detail.update(&self.process.process_list, &self.users, sample);
+    }
+
+    /// Opens the open files view for the selected process.
+    pub fn open_files(&mut self) {
+        let info = match self.process.rows.get(self.process.active_index) {
+            Some(row) if row.group.is_none() && !row.exited => row.info.clone(),
+            _ => return,
+        };
+
+        self.open_files = Some(OpenFiles::new(info.pid, info.name));
+        self.input_mode = InputMode::Files;
+    }
+
+    pub fn close_files(&mut self) {
+        self.open_files = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    pub fn detail_scroll(&mut self, down: bool, n: usize) {
+        if let Some(detail) = self.detail.as_mut() {
+            detail.scroll(down, n);
+        }
+    }
+
+    /// Switches the detail pane tab and reads the new tab's data right away
+    /// rather than on the next refresh.
+    pub fn detail_switch_tab(&mut self, step: isize) {
+        if let Some(detail) = self.detail.as_mut() {
+            detail.switch_tab(step);
+            detail.update(&self.process.process_list, &self.users, None);
+        }
+    }
+
+    pub fn detail_toggle_secrets(&mut self) {
+        if let Some(detail) = self.detail.as_mut() {
+            detail.show_secrets = !detail.show_secrets;
+        }
+    }
+
+    pub fn files_scroll(&mut self, down: bool, n: usize) {
+        if let Some(open_files) = self.open_files.as_mut() {
+            if down {
+                open_files.down(n);
+            } else {
+                open_files.up(n);
+            }
+        }
+    }
+
+    /// Scans every process for deleted executables and libraries.
+    pub fn open_stale(&mut self) {
+        self.rescan_stale();
+        self.input_mode = InputMode::Deleted;
+    }
+
+    /// Rescans for deleted files, and updates the Del column from the same
+    /// scan.
+    pub fn rescan_stale(&mut self) {
+        let cursor = self.stale.as_ref().map_or(0, |stale| stale.cursor);
+        let mut stale = StaleList::scan(&self.process.process_list);
+        stale.cursor = cursor.min(stale.entries.len().saturating_sub(1));
+
+        if self.process.columns.contains(&Column::Deleted) {
+            self.process
+                .deleted_markers
+                .replace(&self.process.process_list, &stale);
+            for p in self.process.process_list.iter_mut() {
+                p.deleted = stale
+                    .entries
+                    .iter()
+                    .find(|entry| entry.pid == p.pid)
+                    .map(|entry| entry.marker());
+            }
+            self.rebuild_rows();
+        }
+        self.stale = Some(stale);
+    }
+
+    pub fn close_stale(&mut self) {
+        self.stale = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    pub fn stale_scroll(&mut self, down: bool, n: usize) {
+        if let Some(stale) = self.stale.as_mut() {
+            stale.scroll(down, n);
+        }
+    }
+
+    /// Enter in any of the text prompts.
+    pub fn confirm_prompt(&mut self) {
+        match self.input_mode {
+            InputMode::Filter => self.confirm_filter(),
+            InputMode::Renice => self.confirm_renice(),
+            InputMode::IoPriority => self.confirm_ioprio(),
+            InputMode::PinPattern => self.confirm_pin_pattern(),
+            InputMode::OomAdj => self.confirm_oom_adj(),
+            _ => {}
+        }
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.status = None;
+    }
+}
+
+async fn set_temp_section(data: &[sysinfo::Component], unit: Temperature) -> Vec<Vec<String>> {
+    //Setting the Temperatures section data
+    data.iter()
+        .map(|x| {
+            vec![
+                x.get_label().to_string(),
+                units::format_temperature(x.get_temperature(), unit),
+            ]
+        })
+        .collect()
 }
 
 async fn set_disk_section(data: &[sysinfo::Disk]) -> Vec<Vec<String>> {
@@ -364,29 +1814,216 @@ async fn set_network_section(data: &sysinfo::Networks) -> (u64, u64) {
 
 async fn set_process_section(
     data: &std::collections::HashMap<sysinfo::Pid, sysinfo::Process>,
+    users: &HashMap<u32, String>,
+    columns: &[Column],
     sort_by: SortBy,
-) -> Vec<(sysinfo::Pid, String, f32, u64)> {
+    sort_order: SortOrder,
+    group_by: GroupBy,
+    threads: ThreadMode,
+    thread_ticks: &mut ThreadTicks,
+    io_counters: &mut IoCounters,
+    deleted_markers: &mut MarkerCache,
+) -> Vec<ProcessInfo> {
     let mut process_list = vec![];
+    io_counters.start();
 
-    for (_, process) in data {
-        process_list.push((
-            process.pid(),
-            process.name().to_string(),
-            process.cpu_usage(),
-            process.disk_usage().total_written_bytes,
-        ));
+    let wants_affinity = columns.contains(&Column::Affinity);
+    let wants_fds = columns.contains(&Column::Fds);
+    let wants_deleted = columns.contains(&Column::Deleted);
+    if wants_deleted {
+        deleted_markers.start();
     }
+    let wants_oom = columns.contains(&Column::OomScore)
+        || columns.contains(&Column::OomAdj)
+        || sort_by == SortBy::OOM
+        || sort_by == SortBy::OOMADJ;
+    let wants_pss = columns.contains(&Column::Pss) || sort_by == SortBy::PSS;
+    let wants_ioprio = columns.contains(&Column::IoPriority);
+    let wants_stat =
+        wants_ioprio || columns.contains(&Column::Nice) || columns.contains(&Column::LastCpu);
 
-    match sort_by {
-        SortBy::CPU => process_list.sort_by(|a, b| {
-            if let Some(x) = (a.2).partial_cmp(&b.2) {
-                x
+    for (_, process) in data {
+        let stat = if wants_stat {
+            procfs::read_stat(process.pid())
+        } else {
+            None
+        };
+        let cpus = if wants_affinity {
+            affinity::get_affinity(process.pid())
+                .map(|cpus| affinity::format_cpu_list(&cpus))
+                .unwrap_or_default()
+        } else {
+            String::new()
+        };
+        let io = process.disk_usage();
+        let (read_rate, write_rate) =
+            io_counters.rates(process.pid(), io.total_read_bytes, io.total_written_bytes);
+        let mut info = ProcessInfo {
+            pid: process.pid(),
+            parent: process.parent(),
+            name: process.name().to_string(),
+            cmd: process.cmd().join(" "),
+            user: users::user_name(users, process.uid),
+            state: status_char(process.status()),
+            nice: stat.as_ref().map(|s| s.nice).unwrap_or(0),
+            last_cpu: stat.as_ref().map(|s| s.processor),
+            ioprio: if wants_ioprio {
+                priority::get_ioprio(process.pid())
             } else {
-                std::cmp::Ordering::Equal
-            }
-        }),
-        SortBy::MEMORY => process_list.sort_by(|a, b| b.3.cmp(&a.3)),
+                None
+            },
+            cpu: process.cpu_usage(),
+            mem: process.memory() * 1024,
+            read_rate,
+            write_rate,
+            read_total: io.total_read_bytes,
+            write_total: io.total_written_bytes,
+            cpus,
+            fds: if wants_fds {
+                files::fd_count(process.pid())
+            } else {
+                None
+            },
+            pss: if wants_pss {
+                procfs::read_smaps_rollup(process.pid()).map(|m| m.pss)
+            } else {
+                None
+            },
+            cgroup: if group_by == GroupBy::Cgroup {
+                procfs::read_cgroup(process.pid())
+            } else {
+                None
+            },
+            ..ProcessInfo::default()
+        };
+        if wants_deleted {
+            info.deleted = deleted_markers.marker(&info);
+        }
+        if wants_oom {
+            info.oom_score = procfs::read_number(info.pid, "oom_score");
+            info.oom_adj = procfs::read_number(info.pid, "oom_score_adj");
+        }
+        process_list.push(info);
     }
 
+    if threads == ThreadMode::Off {
+        *thread_ticks = ThreadTicks::default();
+    } else {
+        process_list = thread_list(&process_list, threads, thread_ticks, wants_ioprio);
+    }
+
+    sort_process_list(&mut process_list, &sort_by, sort_order);
+
     process_list
 }
+
+/// Reads the nice value straight from `/proc`, since the table only keeps
+/// it while the Nice or IO column is shown.
+fn current_nice(pid: sysinfo::Pid) -> i32 {
+    procfs::read_stat(pid).map(|s| s.nice).unwrap_or(0)
+}
+
+/// Replaces each process the thread mode covers by its threads. Threads
+/// share the process's memory, command line, user and cgroup, so those are
+/// copied over; I/O is the thread's own, and counts that would repeat the
+/// process total on every thread (FDs, PSS) are left out. The main thread
+/// keeps the real parent and the others hang off it.
+fn thread_list(
+    process_list: &[ProcessInfo],
+    threads: ThreadMode,
+    thread_ticks: &mut ThreadTicks,
+    wants_ioprio: bool,
+) -> Vec<ProcessInfo> {
+    let owners: HashMap<sysinfo::Pid, &ProcessInfo> = process_list
+        .iter()
+        .filter(|p| threads.includes(p.pid))
+        .map(|p| (p.pid, p))
+        .collect();
+
+    thread_ticks
+        .sample(owners.keys().copied().collect::<Vec<_>>())
+        .into_iter()
+        .filter_map(|thread| {
+            let owner = owners.get(&thread.tgid)?;
+            Some(ProcessInfo {
+                pid: thread.tid,
+                parent: if thread.tid == thread.tgid {
+                    owner.parent
+                } else {
+                    Some(thread.tgid)
+                },
+                name: thread.name,
+                state: thread.stat.state,
+                cpu: thread.cpu,
+                nice: thread.stat.nice,
+                ioprio: if wants_ioprio {
+                    priority::get_ioprio(thread.tid)
+                } else {
+                    None
+                },
+                last_cpu: Some(thread.stat.processor),
+                cpus: if owner.cpus.is_empty() {
+                    String::new()
+                } else {
+                    affinity::get_affinity(thread.tid)
+                        .map(|cpus| affinity::format_cpu_list(&cpus))
+                        .unwrap_or_default()
+                },
+                read_rate: thread.read_rate,
+                write_rate: thread.write_rate,
+                read_total: thread.read_total,
+                write_total: thread.write_total,
+                fds: None,
+                pss: None,
+                ..(*owner).clone()
+            })
+        })
+        .collect()
+}
+
+/// The single-letter state `ps` and `/proc/<pid>/stat` use.
+fn status_char(status: sysinfo::ProcessStatus) -> char {
+    use sysinfo::ProcessStatus::*;
+
+    match status {
+        Run => 'R',
+        Sleep => 'S',
+        Idle => 'I',
+        Zombie => 'Z',
+        Stop => 'T',
+        Tracing => 't',
+        Dead => 'X',
+        Wakekill => 'K',
+        Waking => 'W',
+        Parked => 'P',
+        Unknown(_) => '?',
+    }
+}
+
+pub fn compare_processes(a: &ProcessInfo, b: &ProcessInfo, sort_by: &SortBy) -> Ordering {
+    match sort_by {
+        SortBy::PID => a.pid.cmp(&b.pid),
+        SortBy::NAME => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        SortBy::CPU => a.cpu.partial_cmp(&b.cpu).unwrap_or(Ordering::Equal),
+        SortBy::MEMORY => a.mem.cmp(&b.mem),
+        SortBy::PSS => a.pss.cmp(&b.pss),
+        SortBy::READ => a.read_rate.cmp(&b.read_rate),
+        SortBy::WRITE => a.write_rate.cmp(&b.write_rate),
+        SortBy::IO => (a.read_total + a.write_total).cmp(&(b.read_total + b.write_total)),
+        SortBy::OOM => a.oom_score.cmp(&b.oom_score),
+        SortBy::OOMADJ => a.oom_adj.cmp(&b.oom_adj),
+    }
+}
+
+/// Sorts on the chosen column, falling back to ascending PID so rows with
+/// equal keys do not jump around between refreshes.
+fn sort_process_list(process_list: &mut Vec<ProcessInfo>, sort_by: &SortBy, sort_order: SortOrder) {
+    process_list.sort_by(|a, b| {
+        let ord = compare_processes(a, b, sort_by);
+        let ord = match sort_order {
+            SortOrder::Ascending => ord,
+            SortOrder::Descending => ord.reverse(),
+        };
+        ord.then_with(|| a.pid.cmp(&b.pid))
+    });
+}
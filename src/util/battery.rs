@@ -0,0 +1,43 @@
+use std::fs;
+use std::path::Path;
+
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+#[derive(Debug, Clone)]
+pub struct Battery {
+    pub name: String,
+    /// Charge from 0 to 100.
+    pub percent: u16,
+    /// `Charging`, `Discharging`, `Full`, ...
+    pub status: String,
+}
+
+/// Every battery the kernel reports, sorted by name. Empty on desktops.
+pub fn read_batteries() -> Vec<Battery> {
+    let dir = match fs::read_dir(POWER_SUPPLY) {
+        Ok(dir) => dir,
+        Err(_) => return vec![],
+    };
+
+    let mut batteries: Vec<Battery> = dir
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if read_attr(&path, "type")? != "Battery" {
+                return None;
+            }
+            Some(Battery {
+                name: path.file_name()?.to_string_lossy().into_owned(),
+                percent: read_attr(&path, "capacity")?.parse::<u16>().ok()?.min(100),
+                status: read_attr(&path, "status").unwrap_or_default(),
+            })
+        })
+        .collect();
+    batteries.sort_by(|a, b| a.name.cmp(&b.name));
+    batteries
+}
+
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
@@ -0,0 +1,249 @@
+/// What `main` should do after reading the command line.
+#[derive(Debug)]
+pub enum Command {
+    /// Start the UI with these `(setting, value)` overrides, in order.
+    Run(Vec<(String, String)>),
+    Help,
+    Version,
+}
+
+/// A command-line option. The short forms follow gotop's so rstop can be
+/// dropped in where it was used.
+struct Flag {
+    short: Option<char>,
+    long: &'static str,
+    /// The setting it overrides; flags without `value` set it to `true`.
+    setting: &'static str,
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+const FLAGS: [Flag; 16] = [
+    Flag {
+        short: Some('c'),
+        long: "color",
+        setting: "colorscheme",
+        value: Some("NAME"),
+        help: "Set a colorscheme.",
+    },
+    Flag {
+        short: Some('m'),
+        long: "minimal",
+        setting: "minimal",
+        value: None,
+        help: "Only show CPU, Mem and Process widgets.",
+    },
+    Flag {
+        short: Some('r'),
+        long: "rate",
+        setting: "rate",
+        value: Some("RATE"),
+        help: "Number of times per second to update [default: 4].",
+    },
+    Flag {
+        short: Some('a'),
+        long: "averagecpu",
+        setting: "average_cpu",
+        value: None,
+        help: "Show average CPU in the CPU widget.",
+    },
+    Flag {
+        short: Some('f'),
+        long: "fahrenheit",
+        setting: "fahrenheit",
+        value: None,
+        help: "Show temperatures in fahrenheit.",
+    },
+    Flag {
+        short: Some('s'),
+        long: "statusbar",
+        setting: "statusbar",
+        value: None,
+        help: "Show a statusbar with the time.",
+    },
+    Flag {
+        short: Some('b'),
+        long: "battery",
+        setting: "battery",
+        value: None,
+        help: "Show battery level widget.",
+    },
+    Flag {
+        short: Some('l'),
+        long: "layout",
+        setting: "layout",
+        value: Some("NAME"),
+        help: "Name of layout, or path to a gotop layout file.",
+    },
+    Flag {
+        short: None,
+        long: "no-color",
+        setting: "no_color",
+        value: None,
+        help: "Don't use colours; bold and reverse mark things instead.",
+    },
+    Flag {
+        short: None,
+        long: "color-depth",
+        setting: "color_depth",
+        value: Some("DEPTH"),
+        help: "auto, truecolor, 256, 16 or mono [default: auto].",
+    },
+    Flag {
+        short: None,
+        long: "config",
+        setting: "config",
+        value: Some("PATH"),
+        help: "Read settings from PATH instead of config.toml.",
+    },
+    Flag {
+        short: None,
+        long: "tick-rate",
+        setting: "tick_rate",
+        value: Some("MS"),
+        help: "Milliseconds between updates.",
+    },
+    Flag {
+        short: None,
+        long: "history",
+        setting: "history",
+        value: Some("N"),
+        help: "Samples kept for the charts.",
+    },
+    Flag {
+        short: None,
+        long: "widgets",
+        setting: "widgets",
+        value: Some("LIST"),
+        help: "Comma-separated widgets to show.",
+    },
+    Flag {
+        short: None,
+        long: "temperature",
+        setting: "temperature",
+        value: Some("UNIT"),
+        help: "celsius or fahrenheit.",
+    },
+    Flag {
+        short: None,
+        long: "bytes",
+        setting: "bytes",
+        value: Some("UNIT"),
+        help: "binary or decimal.",
+    },
+];
+
+/// Reads the arguments after the program name. Every problem found is
+/// returned, not just the first.
+pub fn parse(args: &[String]) -> Result<Command, Vec<String>> {
+    let mut overrides = vec![];
+    let mut errors = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Command::Help);
+        }
+        if arg == "-V" || arg == "--version" {
+            return Ok(Command::Version);
+        }
+
+        // `--name value`, `--name=value`, `-r 2`, `-r2` and `-abs`.
+        let (flags, mut inline): (Vec<&Flag>, Option<String>) =
+            if let Some(long) = arg.strip_prefix("--") {
+                let (name, value) = match long.find('=') {
+                    Some(i) => (&long[..i], Some(long[i + 1..].to_string())),
+                    None => (long, None),
+                };
+                match FLAGS.iter().find(|f| f.long == name) {
+                    Some(flag) => (vec![flag], value),
+                    None => {
+                        errors.push(format!("unknown option: --{}", name));
+                        continue;
+                    }
+                }
+            } else if let Some(short) = arg.strip_prefix('-').filter(|s| !s.is_empty()) {
+                let mut flags = vec![];
+                let mut inline = None;
+                for (i, c) in short.char_indices() {
+                    match FLAGS.iter().find(|f| f.short == Some(c)) {
+                        Some(flag) => {
+                            flags.push(flag);
+                            if flag.value.is_some() {
+                                let rest = &short[i + c.len_utf8()..];
+                                if !rest.is_empty() {
+                                    inline = Some(rest.to_string());
+                                }
+                                break;
+                            }
+                        }
+                        None => errors.push(format!("unknown option: -{}", c)),
+                    }
+                }
+                (flags, inline)
+            } else {
+                errors.push(format!("unexpected argument: {}", arg));
+                continue;
+            };
+
+        for flag in flags {
+            if flag.value.is_none() {
+                if inline.take().is_some() {
+                    errors.push(format!("--{} takes no value", flag.long));
+                }
+                overrides.push((flag.setting.to_string(), String::from("true")));
+                continue;
+            }
+            match inline.take().or_else(|| args.next().cloned()) {
+                Some(value) => overrides.push((flag.setting.to_string(), value)),
+                None => errors.push(format!("--{} needs a value", flag.long)),
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(Command::Run(overrides))
+    } else {
+        Err(errors)
+    }
+}
+
+/// How an override from `parse` is spelled on the command line, for errors.
+pub fn flag_name(setting: &str) -> String {
+    match FLAGS.iter().find(|f| f.setting == setting) {
+        Some(flag) => format!("--{}", flag.long),
+        None => setting.to_string(),
+    }
+}
+
+pub fn usage() -> String {
+    let mut usage = String::from("Usage: rstop [options]\n\nOptions:\n");
+    let mut lines: Vec<(String, &str)> = vec![
+        (String::from("-h, --help"), "Show this screen."),
+        (String::from("-V, --version"), "Print version and exit."),
+    ];
+    for flag in FLAGS.iter() {
+        let short = match flag.short {
+            Some(c) => format!("-{}, ", c),
+            None => String::from("    "),
+        };
+        let value = match flag.value {
+            Some(value) => format!("={}", value),
+            None => String::new(),
+        };
+        lines.push((format!("{}--{}{}", short, flag.long, value), flag.help));
+    }
+
+    let width = lines
+        .iter()
+        .map(|(option, _)| option.len())
+        .max()
+        .unwrap_or(0);
+    for (option, help) in lines {
+        usage += &format!("  {:<width$}  {}\n", option, help, width = width);
+    }
+    usage += "\nSettings are read from ~/.config/rstop/config.toml; environment\n";
+    usage += "variables such as RSTOP_TICK_RATE override it, and options override both.\n";
+    usage += "NO_COLOR turns colours off unless color_depth is set.\n";
+    usage
+}
@@ -0,0 +1,164 @@
+use crate::util::app::{ProcessRow, SortBy};
+use crate::util::{priority, units};
+
+/// A column of the process table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Pid,
+    Name,
+    State,
+    Cpu,
+    Mem,
+    Nice,
+    IoPriority,
+    Affinity,
+    Fds,
+    Pss,
+    LastCpu,
+    DiskRead,
+    DiskWrite,
+    DiskTotal,
+    Deleted,
+    OomScore,
+    OomAdj,
+}
+
+/// Every column in the order the column picker lists them.
+pub const ALL_COLUMNS: [Column; 17] = [
+    Column::Pid,
+    Column::Name,
+    Column::State,
+    Column::Cpu,
+    Column::Mem,
+    Column::DiskRead,
+    Column::DiskWrite,
+    Column::DiskTotal,
+    Column::Nice,
+    Column::IoPriority,
+    Column::Affinity,
+    Column::Fds,
+    Column::Pss,
+    Column::LastCpu,
+    Column::Deleted,
+    Column::OomScore,
+    Column::OomAdj,
+];
+
+pub const DEFAULT_COLUMNS: [Column; 8] = [
+    Column::Pid,
+    Column::Name,
+    Column::Cpu,
+    Column::Mem,
+    Column::DiskRead,
+    Column::DiskWrite,
+    Column::Nice,
+    Column::IoPriority,
+];
+
+impl Column {
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Pid => "Pid",
+            Column::Name => "Process",
+            Column::Cpu => "CPU",
+            Column::Mem => "Mem",
+            Column::Nice => "Nice",
+            Column::IoPriority => "IO",
+            Column::Affinity => "CPUs",
+            Column::Fds => "FDs",
+            Column::Pss => "PSS",
+            Column::State => "S",
+            Column::LastCpu => "Last CPU",
+            Column::DiskRead => "Read/s",
+            Column::DiskWrite => "Write/s",
+            Column::DiskTotal => "Disk R/W",
+            Column::Deleted => "Del",
+            Column::OomScore => "OOM",
+            Column::OomAdj => "OOM adj",
+        }
+    }
+
+    /// Whether the column can be hidden from the column picker.
+    pub fn optional(self) -> bool {
+        self != Column::Name
+    }
+
+    /// Share of the table width relative to the other visible columns.
+    pub fn weight(self) -> u32 {
+        match self {
+            Column::Name => 50,
+            Column::DiskTotal => 14,
+            Column::State => 3,
+            Column::Deleted | Column::OomScore => 5,
+            Column::OomAdj => 7,
+            Column::Nice | Column::Fds => 6,
+            _ => 10,
+        }
+    }
+
+    /// The sort key behind this column, if it can be sorted on.
+    pub fn sort_by(self) -> Option<SortBy> {
+        match self {
+            Column::Pid => Some(SortBy::PID),
+            Column::Name => Some(SortBy::NAME),
+            Column::Cpu => Some(SortBy::CPU),
+            Column::Mem => Some(SortBy::MEMORY),
+            Column::Pss => Some(SortBy::PSS),
+            Column::DiskRead => Some(SortBy::READ),
+            Column::DiskWrite => Some(SortBy::WRITE),
+            Column::DiskTotal => Some(SortBy::IO),
+            Column::OomScore => Some(SortBy::OOM),
+            Column::OomAdj => Some(SortBy::OOMADJ),
+            Column::State
+            | Column::Nice
+            | Column::IoPriority
+            | Column::Affinity
+            | Column::Fds
+            | Column::LastCpu
+            | Column::Deleted => None,
+        }
+    }
+
+    /// Cell text for `row`. Group headers leave per-process columns blank.
+    pub fn text(self, row: &ProcessRow) -> String {
+        let p = &row.info;
+        let grouped = row.group.is_some();
+        match self {
+            Column::Pid if grouped => row.members.len().to_string(),
+            Column::Pid => p.pid.to_string(),
+            Column::Name => format!("{}{}", row.prefix, p.name),
+            Column::Cpu => p.cpu.to_string(),
+            Column::Mem => units::format_bytes(p.mem),
+            Column::DiskRead => format!("{}/s", units::format_bytes(p.read_rate)),
+            Column::DiskWrite => format!("{}/s", units::format_bytes(p.write_rate)),
+            Column::DiskTotal => format!(
+                "{}/{}",
+                units::format_bytes(p.read_total),
+                units::format_bytes(p.write_total)
+            ),
+            Column::State
+            | Column::Nice
+            | Column::IoPriority
+            | Column::Affinity
+            | Column::Fds
+            | Column::LastCpu
+            | Column::Deleted
+            | Column::OomScore
+            | Column::OomAdj
+                if grouped =>
+            {
+                String::new()
+            }
+            Column::Nice => p.nice.to_string(),
+            Column::IoPriority => priority::format_ioprio(p.ioprio, p.nice),
+            Column::Affinity => p.cpus.clone(),
+            Column::Fds => p.fds.map(|n| n.to_string()).unwrap_or_default(),
+            Column::Pss => p.pss.map(units::format_bytes).unwrap_or_default(),
+            Column::State => p.state.to_string(),
+            Column::LastCpu => p.last_cpu.map(|c| c.to_string()).unwrap_or_default(),
+            Column::Deleted => p.deleted.map(|m| m.label()).unwrap_or_default().to_string(),
+            Column::OomScore => p.oom_score.map(|s| s.to_string()).unwrap_or_default(),
+            Column::OomAdj => p.oom_adj.map(|s| s.to_string()).unwrap_or_default(),
+        }
+    }
+}
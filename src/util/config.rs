@@ -0,0 +1,10 @@
+use std::path::PathBuf;
+
+/// `$XDG_CONFIG_HOME/rstop/<name>`, falling back to `~/.config/rstop`.
+pub fn config_path(name: &str) -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("rstop").join(name))
+}
@@ -0,0 +1,159 @@
+use std::collections::{BTreeSet, HashMap};
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::util::{app::ProcessInfo, procfs};
+
+const DELETED: &str = " (deleted)";
+
+/// How long the Del column trusts a marker before scanning again.
+const RESCAN_EVERY: Duration = Duration::from_secs(30);
+
+/// Mappings that are deleted by design rather than replaced by an upgrade.
+const IGNORED: [&str; 4] = ["/memfd:", "/dev/", "/SYSV", "/["];
+
+/// What kind of deleted file a process still runs, for the marker column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    Exe,
+    Lib,
+}
+
+impl Marker {
+    pub fn label(self) -> &'static str {
+        match self {
+            Marker::Exe => "exe",
+            Marker::Lib => "lib",
+        }
+    }
+}
+
+/// A process still running code from files that have since been deleted
+/// or replaced, typically by a package upgrade.
+#[derive(Debug, Clone)]
+pub struct Stale {
+    pub pid: sysinfo::Pid,
+    pub name: String,
+    pub user: String,
+    pub exe: Option<String>,
+    pub libs: Vec<String>,
+}
+
+impl Stale {
+    pub fn marker(&self) -> Marker {
+        if self.exe.is_some() {
+            Marker::Exe
+        } else {
+            Marker::Lib
+        }
+    }
+}
+
+/// Checks `/proc/<pid>/exe` and `/proc/<pid>/maps` for `(deleted)` files.
+/// Returns `None` when nothing is stale or the files can't be read.
+pub fn scan(info: &ProcessInfo) -> Option<Stale> {
+    let exe = procfs::read_link(info.pid, "exe")
+        .map(|path| path.display().to_string())
+        .filter(|path| is_stale(path))
+        .map(|path| path.trim_end_matches(DELETED).to_string());
+
+    let libs: BTreeSet<String> = procfs::read_maps(info.pid)
+        .into_iter()
+        .filter(|m| is_stale(&m.path))
+        .map(|m| m.path.trim_end_matches(DELETED).to_string())
+        .filter(|path| Some(path) != exe.as_ref())
+        .collect();
+
+    if exe.is_none() && libs.is_empty() {
+        return None;
+    }
+    Some(Stale {
+        pid: info.pid,
+        name: info.name.clone(),
+        user: info.user.clone(),
+        exe,
+        libs: libs.into_iter().collect(),
+    })
+}
+
+/// Del column markers kept between refreshes, since scanning reads every
+/// process's `maps`. New processes are scanned when first seen and all of
+/// them again every `RESCAN_EVERY`, or from the deleted files view's scan.
+#[derive(Debug, Default)]
+pub struct MarkerCache {
+    previous: HashMap<sysinfo::Pid, Option<Marker>>,
+    current: HashMap<sysinfo::Pid, Option<Marker>>,
+    scanned: Option<Instant>,
+}
+
+impl MarkerCache {
+    /// Starts a new refresh. Processes not seen again are dropped.
+    pub fn start(&mut self) {
+        let now = Instant::now();
+        self.previous = mem::take(&mut self.current);
+        if self
+            .scanned
+            .map_or(true, |at| now.duration_since(at) >= RESCAN_EVERY)
+        {
+            self.previous.clear();
+            self.scanned = Some(now);
+        }
+    }
+
+    pub fn marker(&mut self, info: &ProcessInfo) -> Option<Marker> {
+        let marker = match self.previous.get(&info.pid) {
+            Some(marker) => *marker,
+            None => scan(info).map(|stale| stale.marker()),
+        };
+        self.current.insert(info.pid, marker);
+        marker
+    }
+
+    /// Takes the markers from a full scan, such as the deleted files view's
+    /// rescan, instead of reading everything again on the next refresh.
+    pub fn replace(&mut self, process_list: &[ProcessInfo], stale: &StaleList) {
+        let found: HashMap<sysinfo::Pid, Marker> = stale
+            .entries
+            .iter()
+            .map(|entry| (entry.pid, entry.marker()))
+            .collect();
+        self.current = process_list
+            .iter()
+            .map(|p| (p.pid, found.get(&p.pid).copied()))
+            .collect();
+        self.scanned = Some(Instant::now());
+    }
+}
+
+fn is_stale(path: &str) -> bool {
+    path.starts_with('/')
+        && path.ends_with(DELETED)
+        && !IGNORED.iter().any(|prefix| path.starts_with(prefix))
+}
+
+/// The deleted files view: every affected process, rescanned on demand
+/// because reading every `maps` file is too slow to do on each tick.
+#[derive(Debug, Default)]
+pub struct StaleList {
+    pub entries: Vec<Stale>,
+    pub cursor: usize,
+}
+
+impl StaleList {
+    pub fn scan(process_list: &[ProcessInfo]) -> StaleList {
+        let mut entries: Vec<Stale> = process_list.iter().filter_map(scan).collect();
+        entries.sort_by(|a, b| {
+            (a.marker() != Marker::Exe, a.pid).cmp(&(b.marker() != Marker::Exe, b.pid))
+        });
+        StaleList { entries, cursor: 0 }
+    }
+
+    pub fn scroll(&mut self, down: bool, n: usize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.cursor = if down {
+            (self.cursor + n).min(last)
+        } else {
+            self.cursor.saturating_sub(n)
+        };
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use crate::util::app::ProcessInfo;
+use crate::util::{procfs, security, users};
+
+/// Samples kept for each chart in the detail pane.
+pub const HISTORY_LEN: usize = 120;
+
+/// Pages of the detail pane. Only the visible one's extra files are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetailTab {
+    Overview,
+    Environment,
+    Limits,
+    Security,
+}
+
+impl DetailTab {
+    pub const ALL: [DetailTab; 4] = [
+        DetailTab::Overview,
+        DetailTab::Environment,
+        DetailTab::Limits,
+        DetailTab::Security,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            DetailTab::Overview => "Overview",
+            DetailTab::Environment => "Environment",
+            DetailTab::Limits => "Limits",
+            DetailTab::Security => "Security",
+        }
+    }
+
+    fn index(self) -> usize {
+        DetailTab::ALL.iter().position(|t| *t == self).unwrap_or(0)
+    }
+}
+
+impl Default for DetailTab {
+    fn default() -> Self {
+        DetailTab::Overview
+    }
+}
+
+/// What the detail pane shows for one PID. The static fields are re-read on
+/// every refresh; the histories only grow while the pane is open.
+#[derive(Debug, Default)]
+pub struct Detail {
+    pub pid: sysinfo::Pid,
+    pub name: String,
+    pub cmd: String,
+    pub exe: String,
+    pub cwd: String,
+    pub uids: String,
+    pub gids: String,
+    pub parents: Vec<(sysinfo::Pid, String)>,
+    pub started: Option<u64>,
+    pub state: char,
+    pub threads: u64,
+    pub nice: i32,
+    pub exited: bool,
+    pub cpu_history: Vec<f64>,
+    pub mem_history: Vec<f64>,
+    pub io_history: Vec<f64>,
+    pub memory: Option<procfs::MemoryUsage>,
+    /// Mappings from `/proc/<pid>/maps`, largest first.
+    pub mappings: Vec<procfs::Mapping>,
+    pub environ: Vec<(String, String)>,
+    pub environ_error: Option<String>,
+    /// Shows environment values that look like credentials unmasked.
+    pub show_secrets: bool,
+    pub limits: Vec<(String, procfs::Limit)>,
+    pub security: Vec<(String, String)>,
+    pub tab: DetailTab,
+    /// First row shown of the scrollable list on the current tab.
+    pub scroll: usize,
+}
+
+/// One refresh worth of usage for the process: CPU %, resident bytes and
+/// disk bytes read plus written per second.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub cpu: f32,
+    pub mem: u64,
+    pub io: u64,
+}
+
+impl Detail {
+    pub fn new(info: &ProcessInfo) -> Detail {
+        Detail {
+            pid: info.pid,
+            name: info.name.clone(),
+            ..Detail::default()
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        process_list: &[ProcessInfo],
+        user_names: &HashMap<u32, String>,
+        sample: Option<Sample>,
+    ) {
+        let info = match process_list.iter().find(|p| p.pid == self.pid) {
+            Some(info) => info,
+            None => {
+                self.exited = true;
+                return;
+            }
+        };
+
+        self.name = info.name.clone();
+        self.cmd = info.cmd.clone();
+        self.exe = link_or_unknown(self.pid, "exe");
+        self.cwd = link_or_unknown(self.pid, "cwd");
+
+        let status = procfs::read_status(self.pid);
+        self.uids = format_ids(status.get("Uid"), Some(user_names));
+        self.gids = format_ids(status.get("Gid"), None);
+
+        self.parents = parent_chain(info, process_list);
+
+        match self.tab {
+            DetailTab::Overview => {
+                self.memory = procfs::read_smaps_rollup(self.pid);
+                self.mappings = procfs::read_maps(self.pid);
+                self.mappings
+                    .sort_by(|a, b| b.size().cmp(&a.size()).then(a.start.cmp(&b.start)));
+            }
+            DetailTab::Environment => match procfs::read_environ(self.pid) {
+                Ok(environ) => {
+                    self.environ = environ;
+                    self.environ_error = None;
+                }
+                Err(err) => {
+                    self.environ.clear();
+                    self.environ_error = Some(err.to_string());
+                }
+            },
+            DetailTab::Limits => self.limits = procfs::read_limits(self.pid),
+            DetailTab::Security => self.security = security::read_context(self.pid, &status),
+        }
+        self.scroll = self.scroll.min(self.rows().saturating_sub(1));
+
+        if let Some(stat) = procfs::read_stat(self.pid) {
+            self.state = stat.state;
+            self.nice = stat.nice;
+            self.threads = stat.num_threads;
+            self.started =
+                procfs::boot_time().map(|boot| boot + stat.start_time / procfs::clock_ticks());
+        }
+
+        if let Some(sample) = sample {
+            push_sample(&mut self.cpu_history, sample.cpu as f64);
+            push_sample(&mut self.mem_history, sample.mem as f64);
+            push_sample(&mut self.io_history, sample.io as f64);
+        }
+    }
+
+    /// Length of the scrollable list on the current tab.
+    pub fn rows(&self) -> usize {
+        match self.tab {
+            DetailTab::Overview => self.mappings.len(),
+            DetailTab::Environment => self.environ.len(),
+            DetailTab::Limits => self.limits.len(),
+            DetailTab::Security => self.security.len(),
+        }
+    }
+
+    pub fn scroll(&mut self, down: bool, n: usize) {
+        let last = self.rows().saturating_sub(1);
+        self.scroll = if down {
+            (self.scroll + n).min(last)
+        } else {
+            self.scroll.saturating_sub(n)
+        };
+    }
+
+    /// Moves `step` tabs along, wrapping around.
+    pub fn switch_tab(&mut self, step: isize) {
+        let n = DetailTab::ALL.len() as isize;
+        let i = (self.tab.index() as isize + step).rem_euclid(n);
+        self.tab = DetailTab::ALL[i as usize];
+        self.scroll = 0;
+    }
+}
+
+fn push_sample(history: &mut Vec<f64>, value: f64) {
+    if history.len() >= HISTORY_LEN {
+        history.remove(0);
+    }
+    history.push(value);
+}
+
+fn link_or_unknown(pid: sysinfo::Pid, name: &str) -> String {
+    match procfs::read_link(pid, name) {
+        Some(path) => path.display().to_string(),
+        None => String::from("?"),
+    }
+}
+
+/// Formats the real/effective pair of a `Uid:` or `Gid:` status line,
+/// naming users where a lookup table is given.
+fn format_ids(line: Option<&String>, names: Option<&HashMap<u32, String>>) -> String {
+    let ids: Vec<u32> = match line {
+        Some(line) => line
+            .split_whitespace()
+            .filter_map(|id| id.parse().ok())
+            .collect(),
+        None => return String::from("?"),
+    };
+
+    let name = |id: u32| match names {
+        Some(names) => format!("{} ({})", users::user_name(names, id), id),
+        None => id.to_string(),
+    };
+    match ids.as_slice() {
+        [real, effective, ..] if real != effective => {
+            format!("{} effective {}", name(*real), name(*effective))
+        }
+        [real, ..] => name(*real),
+        [] => String::from("?"),
+    }
+}
+
+/// The ancestors of `info`, outermost first.
+fn parent_chain(info: &ProcessInfo, process_list: &[ProcessInfo]) -> Vec<(sysinfo::Pid, String)> {
+    let mut chain = vec![];
+    let mut current = info.parent;
+    while let Some(pid) = current {
+        if chain.iter().any(|(p, _)| *p == pid) || pid == info.pid {
+            break;
+        }
+        match process_list.iter().find(|p| p.pid == pid) {
+            Some(parent) => {
+                chain.push((parent.pid, parent.name.clone()));
+                current = parent.parent;
+            }
+            None => break,
+        }
+    }
+    chain.reverse();
+    chain
+}
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::mem;
+use std::time::Instant;
+
+/// Read and written byte totals from the previous refresh, used to turn the
+/// cumulative counters into per-second rates.
+#[derive(Debug, Default)]
+pub struct IoCounters {
+    previous: HashMap<sysinfo::Pid, (u64, u64)>,
+    current: HashMap<sysinfo::Pid, (u64, u64)>,
+    at: Option<Instant>,
+    elapsed: f64,
+}
+
+impl IoCounters {
+    /// Starts a new refresh. Processes not seen again are dropped.
+    pub fn start(&mut self) {
+        let now = Instant::now();
+        self.elapsed = self
+            .at
+            .map(|at| now.duration_since(at).as_secs_f64())
+            .unwrap_or(0.0);
+        self.at = Some(now);
+        self.previous = mem::take(&mut self.current);
+    }
+
+    /// Bytes per second read and written by `pid` since the last refresh.
+    /// A process seen for the first time reports 0 rather than its totals.
+    pub fn rates(&mut self, pid: sysinfo::Pid, read: u64, written: u64) -> (u64, u64) {
+        self.current.insert(pid, (read, written));
+        match self.previous.get(&pid) {
+            Some((prev_read, prev_written)) if self.elapsed > 0.0 => (
+                (read.saturating_sub(*prev_read) as f64 / self.elapsed) as u64,
+                (written.saturating_sub(*prev_written) as f64 / self.elapsed) as u64,
+            ),
+            _ => (0, 0),
+        }
+    }
+}
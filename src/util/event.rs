@@ -1,8 +1,11 @@
 use std::{io, sync::mpsc, thread, time::Duration};
-use sysinfo::SystemExt;
 use termion::{event::Key, input::TermRead};
 
-use crate::util::App;
+use crate::util::{
+    app::{InputMode, SortBy},
+    keymap::{Action, Context},
+    App,
+};
 
 #[derive(Debug, PartialEq)]
 pub enum Event {
@@ -13,7 +16,6 @@ pub enum Event {
 #[derive(Debug)]
 pub struct Events {
     rx: mpsc::Receiver<Event>,
-    pub last_event: Event,
 }
 
 pub struct Config {
@@ -32,6 +34,7 @@ impl Default for Config {
 
 impl Events {
     pub fn with_config(config: Config) -> Events {
+        let tick_rate = config.tick_rate;
         let (tx, rx) = mpsc::channel();
         let tx_clone = tx.clone();
         thread::spawn(move || loop {
@@ -53,39 +56,174 @@ impl Events {
             if tx.send(Event::Tick).is_err() {
                 break;
             }
-            thread::sleep(config.tick_rate);
+            thread::sleep(tick_rate);
         });
-        Events {
-            rx,
-            last_event: Event::Input(Key::Char('m')),
-        }
+        Events { rx }
     }
 
     pub fn next_event(&self) -> Result<Event, mpsc::RecvError> {
         self.rx.recv()
     }
 
-    pub fn reset_last_event(&mut self) {
-        self.last_event = Event::Input(Key::Char('m'))
+    pub fn on_key(&mut self, key: &Key, app: &mut App) {
+        let context = match app.input_mode {
+            InputMode::Search => return self.on_search_key(key, app),
+            InputMode::Filter
+            | InputMode::Renice
+            | InputMode::IoPriority
+            | InputMode::PinPattern
+            | InputMode::OomAdj => return self.on_prompt_key(key, app),
+            InputMode::ConfirmSignal => return self.on_confirm_signal_key(key, app),
+            InputMode::Normal => Context::Processes,
+            InputMode::SignalPicker => Context::Signals,
+            InputMode::Affinity => Context::Affinity,
+            InputMode::Columns => Context::Columns,
+            InputMode::Detail => Context::Detail,
+            InputMode::Files => Context::Files,
+            InputMode::Events => Context::Events,
+            InputMode::Deleted => Context::Deleted,
+            InputMode::Help => Context::Help,
+        };
+
+        if context == Context::Processes {
+            // Messages from the last action stay up until the next key press.
+            app.status = None;
+        }
+
+        if let Some((action, count)) = app.keymap.feed(context, *key) {
+            let count = if action.repeats() { count } else { 1 };
+            run_action(app, context, action, count);
+        }
     }
 
-    pub fn on_key<T: SystemExt>(&mut self, key: &Key, app: &mut App, system: &mut T) {
+    fn on_search_key(&mut self, key: &Key, app: &mut App) {
         match key {
-            Key::Char('q') => app.quit(),
-            Key::Char('Q') => app.quit(),
-            Key::Up => app.increase_index(),
-            Key::Down => app.decrease_index(),
-            Key::Char('j') => app.increase_index(),
-            Key::Char('k') => app.decrease_index(),
-            Key::Char('d') => {
-                if self.last_event == Event::Input(Key::Char('d')) {
-                    app.kill(system);
-                    self.reset_last_event();
-                }
-            }
+            Key::Char('\n') => app.confirm_search(),
+            Key::Esc => app.cancel_search(),
+            Key::Backspace => app.search_pop(),
+            Key::Ctrl('r') => app.search_toggle_regex(),
+            Key::Char(c) => app.search_push(*c),
+            Key::Up => app.decrease_index(1),
+            Key::Down => app.increase_index(1),
+            _ => {}
+        }
+    }
+
+    fn on_confirm_signal_key(&mut self, key: &Key, app: &mut App) {
+        match key {
+            Key::Char('y') | Key::Char('Y') => app.send_signal(),
+            _ => app.cancel_signal(),
+        }
+    }
+
+    fn on_prompt_key(&mut self, key: &Key, app: &mut App) {
+        match key {
+            Key::Char('\n') => app.confirm_prompt(),
+            Key::Esc => app.cancel_prompt(),
+            Key::Backspace => app.prompt_pop(),
+            Key::Char(c) => app.prompt_push(*c),
             _ => {}
         }
-        self.last_event = Event::Input(*key);
+    }
+}
+
+/// Runs `action` as it is meant in `context`, `count` steps at a time;
+/// actions that don't apply there are ignored.
+fn run_action(app: &mut App, context: Context, action: Action, count: usize) {
+    // Moving nice by more than its whole range makes no difference.
+    let nice_step = count.min(40) as i32;
+    let page = |rows: usize| rows.saturating_mul(count);
+    match (context, action) {
+        (Context::Processes, Action::Quit) => app.quit(),
+        (Context::Processes, Action::Down) => app.increase_index(count),
+        (Context::Processes, Action::Up) => app.decrease_index(count),
+        (Context::Processes, Action::SortCpu) => app.set_sort(SortBy::CPU),
+        (Context::Processes, Action::SortMemory) => app.set_sort(SortBy::MEMORY),
+        (Context::Processes, Action::SortPid) => app.set_sort(SortBy::PID),
+        (Context::Processes, Action::NextSort) => app.next_sort(count),
+        (Context::Processes, Action::PrevSort) => app.prev_sort(count),
+        (Context::Processes, Action::ReverseSort) => app.reverse_sort(),
+        (Context::Processes, Action::Tree) => app.toggle_tree_view(),
+        (Context::Processes, Action::Collapse) => app.collapse_selected(),
+        (Context::Processes, Action::Expand) => app.expand_selected(),
+        (Context::Processes, Action::Search) => app.start_search(),
+        (Context::Processes, Action::NextMatch) => app.jump_to_match(true, count),
+        (Context::Processes, Action::PrevMatch) => app.jump_to_match(false, count),
+        (Context::Processes, Action::Group) => app.toggle_grouping(),
+        (Context::Processes, Action::Threads) => app.toggle_threads(),
+        (Context::Processes, Action::Filter) => app.start_filter(),
+        (Context::Processes, Action::Signal) => app.open_signal_picker(),
+        (Context::Processes, Action::Tag) => app.toggle_tag(),
+        (Context::Processes, Action::TagMatching) => app.tag_matching(),
+        (Context::Processes, Action::UntagAll) => app.untag_all(),
+        (Context::Processes, Action::Export) => app.export_targets(),
+        (Context::Processes, Action::Renice) => app.start_renice(),
+        (Context::Processes, Action::Ioprio) => app.start_ioprio(),
+        (Context::Processes, Action::NiceDown) => app.nudge_nice(-nice_step),
+        (Context::Processes, Action::NiceUp) => app.nudge_nice(nice_step),
+        (Context::Processes, Action::Affinity) => app.open_affinity(),
+        (Context::Processes, Action::Columns) => app.open_columns(),
+        (Context::Processes, Action::Detail) => app.open_detail(),
+        (Context::Processes, Action::Files) => app.open_files(),
+        (Context::Processes, Action::Events) => app.open_events(),
+        (Context::Processes, Action::Pin) => app.toggle_pin(),
+        (Context::Processes, Action::PinPattern) => app.start_pin_pattern(),
+        (Context::Processes, Action::Deleted) => app.open_stale(),
+        (Context::Processes, Action::OomAdj) => app.start_oom_adj(),
+        (Context::Processes, Action::Help) => app.open_help(),
+
+        (Context::Signals, Action::Confirm) => app.choose_signal(),
+        (Context::Signals, Action::Close) => app.cancel_signal(),
+        (Context::Signals, Action::Up) => app.signal_picker_up(count),
+        (Context::Signals, Action::Down) => app.signal_picker_down(count),
+
+        (Context::Affinity, Action::Confirm) => app.confirm_affinity(),
+        (Context::Affinity, Action::Close) => app.cancel_prompt(),
+        (Context::Affinity, Action::Up) => app.affinity_up(count),
+        (Context::Affinity, Action::Down) => app.affinity_down(count),
+        (Context::Affinity, Action::Toggle) => app.affinity_toggle(),
+        (Context::Affinity, Action::ToggleAll) => app.affinity_toggle_all(),
+
+        (Context::Columns, Action::Close) => app.cancel_prompt(),
+        (Context::Columns, Action::Up) => app.columns_up(count),
+        (Context::Columns, Action::Down) => app.columns_down(count),
+        (Context::Columns, Action::Toggle) => app.toggle_column(),
+
+        (Context::Detail, Action::Close) => app.close_detail(),
+        (Context::Detail, Action::Up) => app.detail_scroll(false, count),
+        (Context::Detail, Action::Down) => app.detail_scroll(true, count),
+        (Context::Detail, Action::PageUp) => app.detail_scroll(false, page(10)),
+        (Context::Detail, Action::PageDown) => app.detail_scroll(true, page(10)),
+        (Context::Detail, Action::NextTab) => app.detail_switch_tab(count as isize),
+        (Context::Detail, Action::PrevTab) => app.detail_switch_tab(-(count as isize)),
+        (Context::Detail, Action::Secrets) => app.detail_toggle_secrets(),
+
+        (Context::Files, Action::Close) => app.close_files(),
+        (Context::Files, Action::Up) => app.files_scroll(false, count),
+        (Context::Files, Action::Down) => app.files_scroll(true, count),
+        (Context::Files, Action::PageUp) => app.files_scroll(false, page(20)),
+        (Context::Files, Action::PageDown) => app.files_scroll(true, page(20)),
+
+        (Context::Events, Action::Close) => app.cancel_prompt(),
+        (Context::Events, Action::Up) => app.events_scroll(false, count),
+        (Context::Events, Action::Down) => app.events_scroll(true, count),
+        (Context::Events, Action::PageUp) => app.events_scroll(false, page(20)),
+        (Context::Events, Action::PageDown) => app.events_scroll(true, page(20)),
+
+        (Context::Deleted, Action::Close) => app.close_stale(),
+        (Context::Deleted, Action::Rescan) => app.rescan_stale(),
+        (Context::Deleted, Action::Up) => app.stale_scroll(false, count),
+        (Context::Deleted, Action::Down) => app.stale_scroll(true, count),
+        (Context::Deleted, Action::PageUp) => app.stale_scroll(false, page(20)),
+        (Context::Deleted, Action::PageDown) => app.stale_scroll(true, page(20)),
+
+        (Context::Help, Action::Close) => app.cancel_prompt(),
+        (Context::Help, Action::Up) => app.help_scroll(false, count),
+        (Context::Help, Action::Down) => app.help_scroll(true, count),
+        (Context::Help, Action::PageUp) => app.help_scroll(false, page(20)),
+        (Context::Help, Action::PageDown) => app.help_scroll(true, page(20)),
+
+        _ => {}
     }
 }
 
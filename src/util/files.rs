@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::util::procfs::{self, Limit};
+
+/// What an entry of `/proc/<pid>/fd` points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FdKind {
+    File,
+    Socket,
+    Pipe,
+    AnonInode,
+    Other,
+}
+
+impl FdKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            FdKind::File => "file",
+            FdKind::Socket => "socket",
+            FdKind::Pipe => "pipe",
+            FdKind::AnonInode => "anon",
+            FdKind::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FdEntry {
+    pub fd: u32,
+    pub kind: FdKind,
+    pub target: String,
+}
+
+/// The open files view for one PID, re-read on every refresh.
+#[derive(Debug, Default)]
+pub struct OpenFiles {
+    pub pid: sysinfo::Pid,
+    pub name: String,
+    pub entries: Vec<FdEntry>,
+    pub limit: Option<Limit>,
+    pub error: Option<String>,
+    pub cursor: usize,
+}
+
+impl OpenFiles {
+    pub fn new(pid: sysinfo::Pid, name: String) -> OpenFiles {
+        let mut files = OpenFiles {
+            pid,
+            name,
+            ..OpenFiles::default()
+        };
+        files.update();
+        files
+    }
+
+    pub fn update(&mut self) {
+        match read_fds(self.pid) {
+            Ok(entries) => {
+                self.entries = entries;
+                self.error = None;
+            }
+            Err(err) => {
+                self.entries.clear();
+                self.error = Some(err.to_string());
+            }
+        }
+        self.limit = procfs::read_limits(self.pid)
+            .into_iter()
+            .find(|(name, _)| name == "Max open files")
+            .map(|(_, limit)| limit);
+        self.cursor = self.cursor.min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn up(&mut self, n: usize) {
+        self.cursor = self.cursor.saturating_sub(n);
+    }
+
+    pub fn down(&mut self, n: usize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.cursor = (self.cursor + n).min(last);
+    }
+
+    /// "12 open, limit 1024/4096 (1012 left, 1% used)" for the view title.
+    pub fn describe(&self) -> String {
+        let open = self.entries.len() as u64;
+        match self.limit.as_ref().and_then(|l| l.soft) {
+            Some(soft) => {
+                let left = soft.saturating_sub(open);
+                let hard = match self.limit.as_ref().and_then(|l| l.hard) {
+                    Some(hard) => hard.to_string(),
+                    None => String::from("unlimited"),
+                };
+                format!(
+                    "{} open, limit {}/{} ({} left, {:.0}% used)",
+                    open,
+                    soft,
+                    hard,
+                    left,
+                    open as f64 * 100.0 / soft.max(1) as f64
+                )
+            }
+            None => format!("{} open", open),
+        }
+    }
+}
+
+/// Number of open descriptors, or `None` when the directory can't be read
+/// (usually another user's process).
+pub fn fd_count(pid: sysinfo::Pid) -> Option<usize> {
+    fs::read_dir(format!("/proc/{}/fd", pid))
+        .ok()
+        .map(|dir| dir.count())
+}
+
+/// Lists the descriptors of `pid` in fd order with their targets resolved.
+/// Sockets are looked up in the process's own network namespace.
+pub fn read_fds(pid: sysinfo::Pid) -> io::Result<Vec<FdEntry>> {
+    let mut sockets = None;
+    let mut entries = vec![];
+
+    for entry in fs::read_dir(format!("/proc/{}/fd", pid))? {
+        let entry = entry?;
+        let fd = match entry.file_name().to_str().and_then(|s| s.parse().ok()) {
+            Some(fd) => fd,
+            None => continue,
+        };
+        // The descriptor may be closed between listing and reading.
+        let link = match fs::read_link(entry.path()) {
+            Ok(link) => link.display().to_string(),
+            Err(_) => continue,
+        };
+
+        let (kind, target) = if let Some(inode) = bracketed(&link, "socket:") {
+            let sockets = sockets.get_or_insert_with(|| read_sockets(pid));
+            let target = match sockets.get(&inode) {
+                Some(socket) => socket.clone(),
+                None => link.clone(),
+            };
+            (FdKind::Socket, target)
+        } else if link.starts_with("pipe:") {
+            (FdKind::Pipe, link)
+        } else if link.starts_with("anon_inode:") {
+            (FdKind::AnonInode, link["anon_inode:".len()..].to_string())
+        } else if link.starts_with('/') {
+            (FdKind::File, link)
+        } else {
+            (FdKind::Other, link)
+        };
+        entries.push(FdEntry { fd, kind, target });
+    }
+
+    entries.sort_by_key(|e| e.fd);
+    Ok(entries)
+}
+
+/// The number in `socket:[1234]` style links.
+fn bracketed(link: &str, prefix: &str) -> Option<u64> {
+    link.strip_prefix(prefix)?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// Socket inode → description, from the `/proc/<pid>/net` tables.
+fn read_sockets(pid: sysinfo::Pid) -> HashMap<u64, String> {
+    let mut sockets = HashMap::new();
+    for proto in &["tcp", "tcp6", "udp", "udp6"] {
+        read_inet_sockets(pid, proto, &mut sockets);
+    }
+    read_unix_sockets(pid, &mut sockets);
+    sockets
+}
+
+fn read_inet_sockets(pid: sysinfo::Pid, proto: &str, sockets: &mut HashMap<u64, String>) {
+    let contents = match fs::read_to_string(format!("/proc/{}/net/{}", pid, proto)) {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+
+    for line in contents.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        let inode: u64 = match fields[9].parse() {
+            Ok(inode) => inode,
+            Err(_) => continue,
+        };
+        let (local, remote) = match (parse_endpoint(fields[1]), parse_endpoint(fields[2])) {
+            (Some(local), Some(remote)) => (local, remote),
+            _ => continue,
+        };
+
+        let state = if proto.starts_with("tcp") {
+            format!(" {}", tcp_state(fields[3]))
+        } else {
+            String::new()
+        };
+        let desc = if fields[2].trim_start_matches('0').starts_with(':') {
+            format!("{} {}{}", proto, local, state)
+        } else {
+            format!("{} {} → {}{}", proto, local, remote, state)
+        };
+        sockets.insert(inode, desc);
+    }
+}
+
+fn read_unix_sockets(pid: sysinfo::Pid, sockets: &mut HashMap<u64, String>) {
+    let contents = match fs::read_to_string(format!("/proc/{}/net/unix", pid)) {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+
+    for line in contents.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 7 {
+            continue;
+        }
+        if let Ok(inode) = fields[6].parse() {
+            let path = fields.get(7).copied().unwrap_or("(unnamed)");
+            sockets.insert(inode, format!("unix {}", path));
+        }
+    }
+}
+
+/// Decodes `0100007F:1F90` (IPv4) or the 32-digit IPv6 form. The address
+/// words are in host byte order, the port is big-endian.
+fn parse_endpoint(s: &str) -> Option<String> {
+    let mut parts = s.split(':');
+    let (addr, port) = (parts.next()?, parts.next()?);
+    let port = u16::from_str_radix(port, 16).ok()?;
+
+    let mut words = vec![];
+    for i in (0..addr.len()).step_by(8) {
+        let word = u32::from_str_radix(addr.get(i..i + 8)?, 16).ok()?;
+        words.push(word.to_ne_bytes());
+    }
+
+    match words.as_slice() {
+        [a] => Some(format!("{}:{}", Ipv4Addr::from(*a), port)),
+        [a, b, c, d] => {
+            let mut octets = [0u8; 16];
+            for (i, word) in [a, b, c, d].iter().enumerate() {
+                octets[i * 4..i * 4 + 4].copy_from_slice(&word[..]);
+            }
+            Some(format!("[{}]:{}", Ipv6Addr::from(octets), port))
+        }
+        _ => None,
+    }
+}
+
+fn tcp_state(hex: &str) -> &'static str {
+    match hex {
+        "01" => "ESTABLISHED",
+        "02" => "SYN_SENT",
+        "03" => "SYN_RECV",
+        "04" => "FIN_WAIT1",
+        "05" => "FIN_WAIT2",
+        "06" => "TIME_WAIT",
+        "07" => "CLOSE",
+        "08" => "CLOSE_WAIT",
+        "09" => "LAST_ACK",
+        "0A" => "LISTEN",
+        "0B" => "CLOSING",
+        _ => "?",
+    }
+}
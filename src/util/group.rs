@@ -0,0 +1,104 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::util::app::{compare_processes, GroupBy, ProcessInfo, ProcessRow, SortBy, SortOrder};
+
+/// Folds `process_list` into one row per group with the member count and
+/// summed usage. Groups are ordered by the active sort column applied to
+/// those totals, or by member count when sorting on PID; members of
+/// expanded groups follow their header in the order they already had.
+pub fn build_group_rows(
+    process_list: &[ProcessInfo],
+    group_by: GroupBy,
+    expanded: &HashSet<String>,
+    sort_by: &SortBy,
+    sort_order: SortOrder,
+) -> Vec<ProcessRow> {
+    let mut order: Vec<String> = vec![];
+    let mut members: HashMap<String, Vec<&ProcessInfo>> = HashMap::new();
+
+    for process in process_list {
+        let key = group_key(process, group_by);
+        if !members.contains_key(&key) {
+            order.push(key.clone());
+        }
+        members.entry(key).or_default().push(process);
+    }
+
+    let mut headers: Vec<ProcessRow> = order
+        .into_iter()
+        .map(|key| {
+            let group = &members[&key];
+            let info = ProcessInfo {
+                pid: group.iter().map(|p| p.pid).min().unwrap_or_default(),
+                name: key.clone(),
+                user: match group_by {
+                    GroupBy::User => key.clone(),
+                    _ => String::new(),
+                },
+                state: ' ',
+                cpu: group.iter().map(|p| p.cpu).sum(),
+                mem: group.iter().map(|p| p.mem).sum(),
+                // Skip members whose smaps_rollup we couldn't read rather
+                // than blanking the whole group.
+                pss: if group.iter().any(|p| p.pss.is_some()) {
+                    Some(group.iter().filter_map(|p| p.pss).sum())
+                } else {
+                    None
+                },
+                read_rate: group.iter().map(|p| p.read_rate).sum(),
+                write_rate: group.iter().map(|p| p.write_rate).sum(),
+                read_total: group.iter().map(|p| p.read_total).sum(),
+                write_total: group.iter().map(|p| p.write_total).sum(),
+                ..ProcessInfo::default()
+            };
+            ProcessRow {
+                collapsed: !expanded.contains(&key),
+                has_children: true,
+                members: group.iter().map(|p| p.pid).collect(),
+                group: Some(key),
+                ..ProcessRow::plain(info)
+            }
+        })
+        .collect();
+
+    headers.sort_by(|a, b| {
+        let ord = match sort_by {
+            // The PID column shows the member count in this view.
+            SortBy::PID => a.members.len().cmp(&b.members.len()),
+            sort_by => compare_processes(&a.info, &b.info, sort_by),
+        };
+        let ord = match sort_order {
+            SortOrder::Ascending => ord,
+            SortOrder::Descending => ord.reverse(),
+        };
+        ord.then_with(|| a.group.cmp(&b.group))
+    });
+
+    let mut rows = vec![];
+    for mut header in headers {
+        let key = header.group.clone().unwrap_or_default();
+        header.prefix = if header.collapsed { "▸ " } else { "▾ " }.to_string();
+        let open = !header.collapsed;
+        rows.push(header);
+
+        if open {
+            for member in &members[&key] {
+                rows.push(ProcessRow {
+                    prefix: String::from("  "),
+                    ..ProcessRow::plain((*member).clone())
+                });
+            }
+        }
+    }
+
+    rows
+}
+
+fn group_key(process: &ProcessInfo, group_by: GroupBy) -> String {
+    match group_by {
+        GroupBy::Name => process.name.clone(),
+        GroupBy::User => process.user.clone(),
+        GroupBy::Cgroup => process.cgroup.clone().unwrap_or_else(|| String::from("?")),
+        GroupBy::Off => process.pid.to_string(),
+    }
+}
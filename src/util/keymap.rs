@@ -0,0 +1,807 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use termion::event::Key;
+
+/// Where a key press goes: the process table or one of the overlays. The
+/// same key can mean different things in each. Text prompts (search,
+/// filter, renice, ...) read keys directly and have no keymap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Context {
+    Processes,
+    Signals,
+    Affinity,
+    Columns,
+    Detail,
+    Files,
+    Events,
+    Deleted,
+    Help,
+}
+
+impl Context {
+    pub const ALL: [Context; 9] = [
+        Context::Processes,
+        Context::Signals,
+        Context::Affinity,
+        Context::Columns,
+        Context::Detail,
+        Context::Files,
+        Context::Events,
+        Context::Deleted,
+        Context::Help,
+    ];
+
+    /// The name of its table in the config file, e.g. `[keys.detail]`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Context::Processes => "processes",
+            Context::Signals => "signals",
+            Context::Affinity => "affinity",
+            Context::Columns => "columns",
+            Context::Detail => "detail",
+            Context::Files => "files",
+            Context::Events => "events",
+            Context::Deleted => "deleted",
+            Context::Help => "help",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Context::Processes => "Process table",
+            Context::Signals => "Signal picker",
+            Context::Affinity => "CPU affinity",
+            Context::Columns => "Column picker",
+            Context::Detail => "Process detail",
+            Context::Files => "Open files",
+            Context::Events => "Event log",
+            Context::Deleted => "Deleted files",
+            Context::Help => "Help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Close,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    SortCpu,
+    SortMemory,
+    SortPid,
+    NextSort,
+    PrevSort,
+    ReverseSort,
+    Tree,
+    Collapse,
+    Expand,
+    Search,
+    NextMatch,
+    PrevMatch,
+    Group,
+    Threads,
+    Filter,
+    Signal,
+    Tag,
+    TagMatching,
+    UntagAll,
+    Export,
+    Renice,
+    Ioprio,
+    NiceDown,
+    NiceUp,
+    Affinity,
+    Columns,
+    Detail,
+    Files,
+    Events,
+    Pin,
+    PinPattern,
+    Deleted,
+    OomAdj,
+    Confirm,
+    Toggle,
+    ToggleAll,
+    NextTab,
+    PrevTab,
+    Secrets,
+    Rescan,
+    Help,
+}
+
+/// Every binding rstop starts with, as context, action, key sequences.
+const DEFAULTS: [(Context, Action, &[&str]); 80] = [
+    (Context::Processes, Action::Quit, &["q", "Q"]),
+    (Context::Processes, Action::Down, &["j", "down"]),
+    (Context::Processes, Action::Up, &["k", "up"]),
+    (Context::Processes, Action::SortCpu, &["c"]),
+    (Context::Processes, Action::SortMemory, &["m"]),
+    (Context::Processes, Action::SortPid, &["p"]),
+    (Context::Processes, Action::NextSort, &[">"]),
+    (Context::Processes, Action::PrevSort, &["<"]),
+    (Context::Processes, Action::ReverseSort, &["I"]),
+    (Context::Processes, Action::Tree, &["t", "f5"]),
+    (Context::Processes, Action::Collapse, &["-", "left"]),
+    (Context::Processes, Action::Expand, &["+", "right"]),
+    (Context::Processes, Action::Search, &["/"]),
+    (Context::Processes, Action::NextMatch, &["n"]),
+    (Context::Processes, Action::PrevMatch, &["N"]),
+    (Context::Processes, Action::Group, &["tab"]),
+    (Context::Processes, Action::Threads, &["H"]),
+    (Context::Processes, Action::Filter, &["f", "f4"]),
+    (Context::Processes, Action::Signal, &["d d", "f9"]),
+    (Context::Processes, Action::Tag, &["space"]),
+    (Context::Processes, Action::TagMatching, &["T"]),
+    (Context::Processes, Action::UntagAll, &["U"]),
+    (Context::Processes, Action::Export, &["e"]),
+    (Context::Processes, Action::Renice, &["r"]),
+    (Context::Processes, Action::Ioprio, &["i"]),
+    (Context::Processes, Action::NiceDown, &["f7"]),
+    (Context::Processes, Action::NiceUp, &["f8"]),
+    (Context::Processes, Action::Affinity, &["a"]),
+    (Context::Processes, Action::Columns, &["o", "f2"]),
+    (Context::Processes, Action::Detail, &["enter"]),
+    (Context::Processes, Action::Files, &["l"]),
+    (Context::Processes, Action::Events, &["E"]),
+    (Context::Processes, Action::Pin, &["P"]),
+    (Context::Processes, Action::PinPattern, &["W"]),
+    (Context::Processes, Action::Deleted, &["D"]),
+    (Context::Processes, Action::OomAdj, &["O"]),
+    (Context::Processes, Action::Help, &["?"]),
+    (Context::Signals, Action::Confirm, &["enter"]),
+    (Context::Signals, Action::Close, &["esc", "q"]),
+    (Context::Signals, Action::Up, &["k", "up"]),
+    (Context::Signals, Action::Down, &["j", "down"]),
+    (Context::Affinity, Action::Confirm, &["enter"]),
+    (Context::Affinity, Action::Close, &["esc", "q"]),
+    (Context::Affinity, Action::Up, &["k", "up"]),
+    (Context::Affinity, Action::Down, &["j", "down"]),
+    (Context::Affinity, Action::Toggle, &["space"]),
+    (Context::Affinity, Action::ToggleAll, &["a"]),
+    (Context::Columns, Action::Close, &["esc", "q", "enter"]),
+    (Context::Columns, Action::Up, &["k", "up"]),
+    (Context::Columns, Action::Down, &["j", "down"]),
+    (Context::Columns, Action::Toggle, &["space"]),
+    (Context::Detail, Action::Close, &["esc", "q", "enter"]),
+    (Context::Detail, Action::Up, &["k", "up"]),
+    (Context::Detail, Action::Down, &["j", "down"]),
+    (Context::Detail, Action::PageUp, &["pageup"]),
+    (Context::Detail, Action::PageDown, &["pagedown"]),
+    (Context::Detail, Action::NextTab, &["tab", "right", "l"]),
+    (Context::Detail, Action::PrevTab, &["backtab", "left", "h"]),
+    (Context::Detail, Action::Secrets, &["s"]),
+    (Context::Files, Action::Close, &["esc", "q", "l"]),
+    (Context::Files, Action::Up, &["k", "up"]),
+    (Context::Files, Action::Down, &["j", "down"]),
+    (Context::Files, Action::PageUp, &["pageup"]),
+    (Context::Files, Action::PageDown, &["pagedown"]),
+    (Context::Events, Action::Close, &["esc", "q", "E"]),
+    (Context::Events, Action::Up, &["k", "up"]),
+    (Context::Events, Action::Down, &["j", "down"]),
+    (Context::Events, Action::PageUp, &["pageup"]),
+    (Context::Events, Action::PageDown, &["pagedown"]),
+    (Context::Deleted, Action::Close, &["esc", "q", "D"]),
+    (Context::Deleted, Action::Rescan, &["r"]),
+    (Context::Deleted, Action::Up, &["k", "up"]),
+    (Context::Deleted, Action::Down, &["j", "down"]),
+    (Context::Deleted, Action::PageUp, &["pageup"]),
+    (Context::Deleted, Action::PageDown, &["pagedown"]),
+    (Context::Help, Action::Close, &["esc", "q", "?"]),
+    (Context::Help, Action::Up, &["k", "up"]),
+    (Context::Help, Action::Down, &["j", "down"]),
+    (Context::Help, Action::PageUp, &["pageup"]),
+    (Context::Help, Action::PageDown, &["pagedown"]),
+];
+
+impl Action {
+    /// The name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Close => "close",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::SortCpu => "sort_cpu",
+            Action::SortMemory => "sort_memory",
+            Action::SortPid => "sort_pid",
+            Action::NextSort => "next_sort",
+            Action::PrevSort => "prev_sort",
+            Action::ReverseSort => "reverse_sort",
+            Action::Tree => "tree",
+            Action::Collapse => "collapse",
+            Action::Expand => "expand",
+            Action::Search => "search",
+            Action::NextMatch => "next_match",
+            Action::PrevMatch => "prev_match",
+            Action::Group => "group",
+            Action::Threads => "threads",
+            Action::Filter => "filter",
+            Action::Signal => "signal",
+            Action::Tag => "tag",
+            Action::TagMatching => "tag_matching",
+            Action::UntagAll => "untag_all",
+            Action::Export => "export",
+            Action::Renice => "renice",
+            Action::Ioprio => "ioprio",
+            Action::NiceDown => "nice_down",
+            Action::NiceUp => "nice_up",
+            Action::Affinity => "affinity",
+            Action::Columns => "columns",
+            Action::Detail => "detail",
+            Action::Files => "files",
+            Action::Events => "events",
+            Action::Pin => "pin",
+            Action::PinPattern => "pin_pattern",
+            Action::Deleted => "deleted",
+            Action::OomAdj => "oom_adj",
+            Action::Confirm => "confirm",
+            Action::Toggle => "toggle",
+            Action::ToggleAll => "toggle_all",
+            Action::NextTab => "next_tab",
+            Action::PrevTab => "prev_tab",
+            Action::Secrets => "secrets",
+            Action::Rescan => "rescan",
+            Action::Help => "help",
+        }
+    }
+
+    /// What it does, for the help overlay.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Close => "close",
+            Action::Up => "move up",
+            Action::Down => "move down",
+            Action::PageUp => "page up",
+            Action::PageDown => "page down",
+            Action::SortCpu => "sort by CPU",
+            Action::SortMemory => "sort by memory",
+            Action::SortPid => "sort by PID",
+            Action::NextSort => "next sort column",
+            Action::PrevSort => "previous sort column",
+            Action::ReverseSort => "reverse sort direction",
+            Action::Tree => "toggle tree view",
+            Action::Collapse => "collapse subtree or group",
+            Action::Expand => "expand subtree or group",
+            Action::Search => "search",
+            Action::NextMatch => "next search match",
+            Action::PrevMatch => "previous search match",
+            Action::Group => "group by command, user or cgroup",
+            Action::Threads => "show threads",
+            Action::Filter => "filter expression",
+            Action::Signal => "send a signal",
+            Action::Tag => "tag process",
+            Action::TagMatching => "tag matching processes",
+            Action::UntagAll => "untag all",
+            Action::Export => "export tagged processes",
+            Action::Renice => "set nice value",
+            Action::Ioprio => "set I/O priority",
+            Action::NiceDown => "nice -1",
+            Action::NiceUp => "nice +1",
+            Action::Affinity => "edit CPU affinity",
+            Action::Columns => "choose columns",
+            Action::Detail => "process detail",
+            Action::Files => "open files",
+            Action::Events => "spawn/exit log",
+            Action::Pin => "pin or unpin",
+            Action::PinPattern => "pin by pattern",
+            Action::Deleted => "processes using deleted files",
+            Action::OomAdj => "set oom_score_adj",
+            Action::Confirm => "confirm",
+            Action::Toggle => "toggle",
+            Action::ToggleAll => "toggle all",
+            Action::NextTab => "next tab",
+            Action::PrevTab => "previous tab",
+            Action::Secrets => "show or mask secrets",
+            Action::Rescan => "rescan",
+            Action::Help => "this help",
+        }
+    }
+
+    /// Whether a count such as the 5 in `5j` applies to the action, which
+    /// then moves that many steps at once. Other actions ignore it.
+    pub fn repeats(self) -> bool {
+        matches!(
+            self,
+            Action::Up
+                | Action::Down
+                | Action::PageUp
+                | Action::PageDown
+                | Action::NextSort
+                | Action::PrevSort
+                | Action::NextMatch
+                | Action::PrevMatch
+                | Action::NiceDown
+                | Action::NiceUp
+                | Action::NextTab
+                | Action::PrevTab
+        )
+    }
+}
+
+/// Key sequences for one action in the config file: `"x"`, `"d d"` or a
+/// list of them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Sequences {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Sequences {
+    fn as_slice(&self) -> &[String] {
+        match self {
+            Sequences::One(sequence) => std::slice::from_ref(sequence),
+            Sequences::Many(sequences) => sequences,
+        }
+    }
+}
+
+/// An entry of the `[keys]` table: either an action of the process table,
+/// or a `[keys.<context>]` table of actions.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeysEntry {
+    Action(Sequences),
+    Context(BTreeMap<String, Sequences>),
+}
+
+/// One line of the help overlay.
+#[derive(Debug, Clone)]
+pub enum HelpLine {
+    Section(Context),
+    /// `keys` is empty when the action is unbound.
+    Entry {
+        keys: String,
+        action: Action,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub keys: Vec<Key>,
+    pub action: Action,
+}
+
+/// The largest count a key sequence can carry; further digits are ignored.
+const MAX_COUNT: usize = 9999;
+
+/// Maps key sequences to actions per context and tracks a partly typed
+/// sequence and its count.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<Context, Vec<Binding>>,
+    /// How long a partly typed sequence or count is kept.
+    timeout: Duration,
+    pending: Vec<Key>,
+    count: Option<usize>,
+    last_key: Option<Instant>,
+}
+
+impl Keymap {
+    /// The defaults with the `[keys]` table applied. Binding an action
+    /// replaces its default keys; an empty list unbinds it. Unknown names,
+    /// bad keys and conflicts are all reported.
+    pub fn build(
+        keys: &BTreeMap<String, KeysEntry>,
+        timeout: Duration,
+    ) -> Result<Keymap, Vec<String>> {
+        let mut errors = vec![];
+
+        // Sequences per default binding, in the order of `DEFAULTS`.
+        let mut actions: Vec<(Context, Action, Vec<Vec<Key>>)> = DEFAULTS
+            .iter()
+            .map(|(context, action, sequences)| {
+                let sequences = sequences
+                    .iter()
+                    .map(|s| parse_sequence(s).expect("bad default binding"))
+                    .collect();
+                (*context, *action, sequences)
+            })
+            .collect();
+
+        let mut overrides: Vec<(Context, &str, &Sequences)> = vec![];
+        for (name, entry) in keys {
+            match entry {
+                KeysEntry::Action(sequences) => {
+                    overrides.push((Context::Processes, name, sequences))
+                }
+                KeysEntry::Context(table) => match Context::ALL.iter().find(|c| c.name() == name) {
+                    Some(context) => overrides.extend(
+                        table
+                            .iter()
+                            .map(|(action, sequences)| (*context, action.as_str(), sequences)),
+                    ),
+                    None => errors.push(format!("keys.{}: no such context", name)),
+                },
+            }
+        }
+
+        for (context, name, sequences) in overrides {
+            let label = if context == Context::Processes {
+                format!("keys.{}", name)
+            } else {
+                format!("keys.{}.{}", context.name(), name)
+            };
+            let entry = match actions
+                .iter_mut()
+                .find(|(c, action, _)| *c == context && action.name() == name)
+            {
+                Some(entry) => entry,
+                None => {
+                    errors.push(format!("{}: no such action in {}", label, context.name()));
+                    continue;
+                }
+            };
+            let mut parsed = vec![];
+            for sequence in sequences.as_slice() {
+                match parse_sequence(sequence) {
+                    Ok(keys) => parsed.push(keys),
+                    Err(err) => errors.push(format!("{}: {}", label, err)),
+                }
+            }
+            entry.2 = parsed;
+        }
+
+        let mut bindings: HashMap<Context, Vec<Binding>> = HashMap::new();
+        for (context, action, sequences) in actions {
+            let list = bindings.entry(context).or_default();
+            for keys in sequences {
+                list.push(Binding { keys, action });
+            }
+        }
+        errors.extend(find_conflicts(&bindings));
+
+        if errors.is_empty() {
+            Ok(Keymap {
+                bindings,
+                timeout,
+                pending: vec![],
+                count: None,
+                last_key: None,
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The help overlay's lines: each context with its actions and their
+    /// current keys, unbound actions included.
+    pub fn help(&self) -> Vec<HelpLine> {
+        let mut lines = vec![];
+        for context in Context::ALL.iter() {
+            lines.push(HelpLine::Section(*context));
+            let bindings = self.bindings.get(context).map(Vec::as_slice).unwrap_or(&[]);
+            for (_, action, _) in DEFAULTS.iter().filter(|(c, _, _)| c == context) {
+                let keys: Vec<String> = bindings
+                    .iter()
+                    .filter(|b| b.action == *action)
+                    .map(|b| sequence_label(&b.keys))
+                    .collect();
+                lines.push(HelpLine::Entry {
+                    keys: keys.join(", "),
+                    action: *action,
+                });
+            }
+        }
+        lines
+    }
+
+    /// Feeds one key press. Returns the action and its count once a whole
+    /// sequence has been typed.
+    pub fn feed(&mut self, context: Context, key: Key) -> Option<(Action, usize)> {
+        let now = Instant::now();
+        if self
+            .last_key
+            .map_or(false, |at| now.duration_since(at) > self.timeout)
+        {
+            self.reset();
+        }
+        self.last_key = Some(now);
+
+        let bindings = self
+            .bindings
+            .get(&context)
+            .map(Vec::as_slice)
+            .unwrap_or(&[]);
+
+        // Digits start a count unless something is bound to them.
+        if let Key::Char(c @ '0'..='9') = key {
+            let starts_count = self.count.is_some() || c != '0';
+            let bound = bindings.iter().any(|b| b.keys[0] == key);
+            if self.pending.is_empty() && starts_count && !bound {
+                let digit = c as usize - '0' as usize;
+                self.count = Some(
+                    self.count
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit)
+                        .min(MAX_COUNT),
+                );
+                return None;
+            }
+        }
+
+        self.pending.push(key);
+        let pending = &self.pending;
+        if let Some(binding) = bindings.iter().find(|b| b.keys == *pending) {
+            let found = (binding.action, self.count.unwrap_or(1).max(1));
+            self.reset();
+            return Some(found);
+        }
+        if bindings.iter().any(|b| b.keys.starts_with(pending)) {
+            return None;
+        }
+
+        // A key that breaks a sequence starts over on its own.
+        let retry = self.pending.len() > 1;
+        self.reset();
+        if retry {
+            self.feed(context, key)
+        } else {
+            None
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.count = None;
+    }
+}
+
+/// The same sequence bound twice, or one sequence that starts another, in
+/// one context.
+fn find_conflicts(bindings: &HashMap<Context, Vec<Binding>>) -> Vec<String> {
+    let mut errors = vec![];
+    for context in Context::ALL.iter() {
+        let list = match bindings.get(context) {
+            Some(list) => list,
+            None => continue,
+        };
+        for (i, a) in list.iter().enumerate() {
+            for b in &list[i + 1..] {
+                if a.keys == b.keys {
+                    errors.push(format!(
+                        "keys.{}: `{}` is bound to both {} and {}",
+                        context.name(),
+                        sequence_label(&a.keys),
+                        a.action.name(),
+                        b.action.name()
+                    ));
+                } else if a.keys.starts_with(&b.keys) || b.keys.starts_with(&a.keys) {
+                    let (short, long) = if a.keys.len() < b.keys.len() {
+                        (a, b)
+                    } else {
+                        (b, a)
+                    };
+                    errors.push(format!(
+                        "keys.{}: `{}` ({}) can never be typed because `{}` ({}) fires first",
+                        context.name(),
+                        sequence_label(&long.keys),
+                        long.action.name(),
+                        sequence_label(&short.keys),
+                        short.action.name()
+                    ));
+                }
+            }
+        }
+    }
+    errors
+}
+
+/// Parses a key sequence as written in the config file: keys separated by
+/// spaces, e.g. `d d`, `ctrl-r` or `g g`.
+pub fn parse_sequence(s: &str) -> Result<Vec<Key>, String> {
+    let keys = s
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<Vec<Key>, String>>()?;
+    if keys.is_empty() {
+        return Err(String::from("empty key sequence"));
+    }
+    Ok(keys)
+}
+
+/// Parses one key: a single character, or a name such as `enter`, `f5` or
+/// `ctrl-r`.
+pub fn parse_key(s: &str) -> Result<Key, String> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(Key::Char(c));
+    }
+
+    let lower = s.to_lowercase();
+    let key = match lower.as_str() {
+        "enter" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "space" => Key::Char(' '),
+        "backtab" => Key::BackTab,
+        "esc" => Key::Esc,
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        "insert" => Key::Insert,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        _ => {
+            let single = |rest: &str| {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(c),
+                    _ => None,
+                }
+            };
+            if let Some(c) = lower.strip_prefix("ctrl-").and_then(single) {
+                Key::Ctrl(c)
+            } else if let Some(c) = lower.strip_prefix("alt-").and_then(single) {
+                Key::Alt(c)
+            } else if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                if !(1..=12).contains(&n) {
+                    return Err(format!("no such function key: {}", s));
+                }
+                Key::F(n)
+            } else {
+                return Err(format!("unknown key: {}", s));
+            }
+        }
+    };
+    Ok(key)
+}
+
+/// How a key is shown in messages and the help overlay.
+pub fn key_label(key: Key) -> String {
+    match key {
+        Key::Char('\n') => String::from("Enter"),
+        Key::Char('\t') => String::from("Tab"),
+        Key::Char(' ') => String::from("Space"),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("Ctrl-{}", c),
+        Key::Alt(c) => format!("Alt-{}", c),
+        Key::F(n) => format!("F{}", n),
+        Key::BackTab => String::from("Shift-Tab"),
+        Key::Esc => String::from("Esc"),
+        Key::Backspace => String::from("Backspace"),
+        Key::Delete => String::from("Delete"),
+        Key::Insert => String::from("Insert"),
+        Key::Home => String::from("Home"),
+        Key::End => String::from("End"),
+        Key::PageUp => String::from("PgUp"),
+        Key::PageDown => String::from("PgDn"),
+        Key::Up => String::from("Up"),
+        Key::Down => String::from("Down"),
+        Key::Left => String::from("Left"),
+        Key::Right => String::from("Right"),
+        _ => String::from("?"),
+    }
+}
+
+pub fn sequence_label(keys: &[Key]) -> String {
+    keys.iter()
+        .map(|&k| key_label(k))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(keys: &[(&str, &str)]) -> Result<Keymap, Vec<String>> {
+        let keys = keys
+            .iter()
+            .map(|(action, sequence)| {
+                (
+                    action.to_string(),
+                    KeysEntry::Action(Sequences::One(sequence.to_string())),
+                )
+            })
+            .collect();
+        Keymap::build(&keys, Duration::from_secs(60))
+    }
+
+    fn feed(keymap: &mut Keymap, keys: &str) -> Option<(Action, usize)> {
+        let mut found = None;
+        for c in keys.chars() {
+            found = keymap.feed(Context::Processes, Key::Char(c));
+        }
+        found
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(parse_key("x"), Ok(Key::Char('x')));
+        assert_eq!(parse_key("Enter"), Ok(Key::Char('\n')));
+        assert_eq!(parse_key("ctrl-r"), Ok(Key::Ctrl('r')));
+        assert_eq!(parse_key("alt-x"), Ok(Key::Alt('x')));
+        assert_eq!(parse_key("f12"), Ok(Key::F(12)));
+        assert_eq!(parse_key("pagedown"), Ok(Key::PageDown));
+
+        assert!(parse_key("f13").is_err());
+        assert!(parse_key("ctrl-rr").is_err());
+        assert!(parse_key("hyper-x").is_err());
+        assert!(parse_key("").is_err());
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(
+            parse_sequence("g g"),
+            Ok(vec![Key::Char('g'), Key::Char('g')])
+        );
+        assert_eq!(parse_sequence("  ctrl-x  "), Ok(vec![Key::Ctrl('x')]));
+
+        assert!(parse_sequence("").is_err());
+        assert!(parse_sequence("   ").is_err());
+        assert!(parse_sequence("d bogus").is_err());
+    }
+
+    #[test]
+    fn counts() {
+        let mut keymap = build(&[]).unwrap();
+        assert_eq!(feed(&mut keymap, "j"), Some((Action::Down, 1)));
+        assert_eq!(feed(&mut keymap, "5j"), Some((Action::Down, 5)));
+        assert_eq!(
+            feed(&mut keymap, "1234567j"),
+            Some((Action::Down, MAX_COUNT))
+        );
+
+        // The count comes back either way; the caller only applies it to
+        // actions that repeat.
+        assert_eq!(feed(&mut keymap, "3e"), Some((Action::Export, 3)));
+        assert!(Action::Down.repeats());
+        assert!(!Action::Export.repeats());
+    }
+
+    #[test]
+    fn multi_key_sequences() {
+        let mut keymap = build(&[]).unwrap();
+        assert_eq!(feed(&mut keymap, "d"), None);
+        assert_eq!(feed(&mut keymap, "d"), Some((Action::Signal, 1)));
+
+        // A key that doesn't continue the sequence runs on its own.
+        assert_eq!(feed(&mut keymap, "dj"), Some((Action::Down, 1)));
+        assert_eq!(feed(&mut keymap, "dx"), None);
+        assert_eq!(feed(&mut keymap, "j"), Some((Action::Down, 1)));
+    }
+
+    #[test]
+    fn overrides() {
+        let mut keymap = build(&[("down", "x")]).unwrap();
+        assert_eq!(feed(&mut keymap, "x"), Some((Action::Down, 1)));
+        assert_eq!(feed(&mut keymap, "j"), None);
+
+        let errors = build(&[("bogus", "x"), ("down", "f99")]).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn conflicts() {
+        let errors = build(&[("tag", "d")]).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![String::from(
+                "keys.processes: `d d` (signal) can never be typed because `d` (tag) fires first"
+            )]
+        );
+
+        let errors = build(&[("tag", "j")]).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![String::from(
+                "keys.processes: `j` is bound to both down and tag"
+            )]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::util::app::ProcessInfo;
+
+/// How long new rows stay highlighted and exited ones stay in the table.
+pub const HIGHLIGHT_FOR: Duration = Duration::from_secs(5);
+
+/// Spawn/exit events kept for the log view.
+const LOG_LEN: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct LifeEvent {
+    /// Seconds since the epoch.
+    pub at: u64,
+    pub spawned: bool,
+    pub pid: sysinfo::Pid,
+    pub name: String,
+    pub cmd: String,
+}
+
+/// Diffs the PID set between refreshes to find spawned and exited
+/// processes.
+#[derive(Debug, Default)]
+pub struct Lifecycle {
+    known: HashSet<sysinfo::Pid>,
+    primed: bool,
+    spawned: HashMap<sysinfo::Pid, Instant>,
+    /// Exited processes with their last stats, shown greyed out for a while.
+    exited: HashMap<sysinfo::Pid, (ProcessInfo, Instant)>,
+    /// Newest last.
+    pub log: VecDeque<LifeEvent>,
+    pub cursor: usize,
+}
+
+impl Lifecycle {
+    /// Compares `current` with the previous refresh. `previous` is the list
+    /// as last shown, so it is where the exited processes' stats come from.
+    pub fn update(&mut self, previous: &[ProcessInfo], current: &[ProcessInfo]) {
+        let now = Instant::now();
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let pids: HashSet<sysinfo::Pid> = current.iter().map(|p| p.pid).collect();
+        let known = mem::take(&mut self.known);
+
+        // Everything is new on the first refresh; don't flag it.
+        if self.primed {
+            for p in current.iter().filter(|p| !known.contains(&p.pid)) {
+                self.spawned.insert(p.pid, now);
+                self.push(LifeEvent::new(at, true, p));
+            }
+            for p in previous
+                .iter()
+                .filter(|p| known.contains(&p.pid) && !pids.contains(&p.pid))
+            {
+                self.exited.insert(p.pid, (p.clone(), now));
+                self.push(LifeEvent::new(at, false, p));
+            }
+        }
+        self.primed = true;
+        self.known = pids;
+
+        self.spawned
+            .retain(|_, seen| now.duration_since(*seen) < HIGHLIGHT_FOR);
+        let known = &self.known;
+        self.exited.retain(|pid, (_, seen)| {
+            now.duration_since(*seen) < HIGHLIGHT_FOR && !known.contains(pid)
+        });
+    }
+
+    /// The recently exited processes with their last stats. They are only
+    /// shown, never part of the process list actions work on.
+    pub fn exited(&self) -> impl Iterator<Item = &ProcessInfo> {
+        self.exited.values().map(|(p, _)| p)
+    }
+
+    /// Forgets the PID set so the next refresh flags nothing, e.g. when
+    /// the table switches between processes and threads.
+    pub fn rebase(&mut self) {
+        self.primed = false;
+        self.spawned.clear();
+        self.exited.clear();
+    }
+
+    pub fn is_new(&self, pid: sysinfo::Pid) -> bool {
+        self.spawned.contains_key(&pid)
+    }
+
+    fn push(&mut self, event: LifeEvent) {
+        if self.log.len() >= LOG_LEN {
+            self.log.pop_front();
+        }
+        self.log.push_back(event);
+    }
+
+    pub fn scroll(&mut self, down: bool, n: usize) {
+        let last = self.log.len().saturating_sub(1);
+        self.cursor = if down {
+            (self.cursor + n).min(last)
+        } else {
+            self.cursor.saturating_sub(n)
+        };
+    }
+}
+
+impl LifeEvent {
+    fn new(at: u64, spawned: bool, p: &ProcessInfo) -> LifeEvent {
+        LifeEvent {
+            at,
+            spawned,
+            pid: p.pid,
+            name: p.name.clone(),
+            cmd: p.cmd.clone(),
+        }
+    }
+}
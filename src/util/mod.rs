@@ -1,4 +1,29 @@
+pub mod affinity;
 pub mod app;
+pub mod battery;
+pub mod cli;
+pub mod columns;
+pub mod config;
+pub mod deleted;
+pub mod detail;
+pub mod diskio;
 pub mod event;
+pub mod files;
+pub mod group;
+pub mod keymap;
+pub mod lifecycle;
+pub mod pins;
+pub mod priority;
+pub mod procfs;
+pub mod query;
+pub mod search;
+pub mod security;
+pub mod settings;
+pub mod signal;
+pub mod theme;
+pub mod threads;
+pub mod tree;
 pub mod ui;
+pub mod units;
+pub mod users;
 pub use app::App;
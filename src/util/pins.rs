@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+
+use crate::util::{app::ProcessInfo, config};
+
+/// A watchlist entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pin {
+    /// One process. The second field is its command line, or its name for
+    /// kernel threads, which tells a reused PID apart and finds the program
+    /// again after a restart.
+    Pid(sysinfo::Pid, String),
+    /// Case-insensitive substring of the name or command line.
+    Pattern(String),
+}
+
+impl Pin {
+    pub fn for_process(p: &ProcessInfo) -> Pin {
+        Pin::Pid(p.pid, identity(p))
+    }
+
+    pub fn matches(&self, p: &ProcessInfo) -> bool {
+        match self {
+            // Checking the program too keeps a reused PID from inheriting
+            // the pin before it is re-attached.
+            Pin::Pid(pid, program) => *pid == p.pid && is_program(p, program),
+            Pin::Pattern(pattern) => {
+                let pattern = pattern.to_lowercase();
+                p.name.to_lowercase().contains(&pattern) || p.cmd.to_lowercase().contains(&pattern)
+            }
+        }
+    }
+
+    /// Moves a PID pin whose process is gone, or whose PID now belongs to
+    /// another program, onto a process with the same command line that no
+    /// other pin holds. Returns whether the pin changed.
+    pub fn reattach(
+        &mut self,
+        process_list: &[ProcessInfo],
+        taken: &HashSet<sysinfo::Pid>,
+    ) -> bool {
+        let (pid, wanted) = match self {
+            Pin::Pid(pid, wanted) => (pid, wanted),
+            Pin::Pattern(_) => return false,
+        };
+        if process_list
+            .iter()
+            .any(|p| p.pid == *pid && is_program(p, wanted))
+        {
+            return false;
+        }
+
+        match process_list
+            .iter()
+            .filter(|p| !taken.contains(&p.pid) && is_program(p, wanted))
+            .map(|p| p.pid)
+            .min()
+        {
+            Some(found) if found != *pid => {
+                *pid = found;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        match self {
+            Pin::Pid(pid, identity) => format!("pid = {} {}", pid, identity),
+            Pin::Pattern(pattern) => format!("pattern = {}", pattern),
+        }
+    }
+
+    fn from_line(line: &str) -> Option<Pin> {
+        let i = line.find('=')?;
+        let value = line[i + 1..].trim().to_string();
+        match line[..i].trim() {
+            "pid" => {
+                let mut parts = value.splitn(2, ' ');
+                let pid = parts.next()?.parse().ok()?;
+                Some(Pin::Pid(pid, parts.next()?.trim().to_string()))
+            }
+            "pattern" => Some(Pin::Pattern(value)),
+            _ => None,
+        }
+    }
+}
+
+/// What a PID pin re-matches on: the command line, or the name for kernel
+/// threads, which have none.
+fn identity(p: &ProcessInfo) -> String {
+    if p.cmd.trim().is_empty() {
+        p.name.clone()
+    } else {
+        p.cmd.trim().to_string()
+    }
+}
+
+fn is_program(p: &ProcessInfo, identity: &str) -> bool {
+    match p.cmd.trim() {
+        "" => p.name == identity,
+        cmd => cmd == identity,
+    }
+}
+
+/// Reads the watchlist, one `pid = <pid> <command line>` or
+/// `pattern = ...` per line.
+///
+/// Pins live in their own file next to `filters` rather than in
+/// `config.toml`: rstop rewrites the file every time a pin is toggled, and
+/// doing that to the config would throw away the user's comments and
+/// layout.
+pub fn load_pins() -> Vec<Pin> {
+    let contents = match config::config_path("pins").map(fs::read_to_string) {
+        Some(Ok(contents)) => contents,
+        _ => return vec![],
+    };
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(Pin::from_line)
+        .collect()
+}
+
+pub fn store_pins(pins: &[Pin]) -> io::Result<()> {
+    let path = config::config_path("pins")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut contents = String::new();
+    for pin in pins {
+        contents += &pin.to_line();
+        contents += "\n";
+    }
+    fs::write(path, contents)
+}
@@ -0,0 +1,133 @@
+use std::io;
+
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+const IOPRIO_PRIO_MASK: i32 = (1 << IOPRIO_CLASS_SHIFT) - 1;
+
+/// I/O scheduling classes as understood by `ioprio_set(2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoClass {
+    None,
+    RealTime,
+    BestEffort,
+    Idle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoPriority {
+    pub class: IoClass,
+    pub level: i32,
+}
+
+impl IoPriority {
+    fn from_raw(raw: i32) -> IoPriority {
+        let class = match raw >> IOPRIO_CLASS_SHIFT {
+            1 => IoClass::RealTime,
+            2 => IoClass::BestEffort,
+            3 => IoClass::Idle,
+            _ => IoClass::None,
+        };
+        IoPriority {
+            class,
+            level: raw & IOPRIO_PRIO_MASK,
+        }
+    }
+
+    fn to_raw(self) -> i32 {
+        let class = match self.class {
+            IoClass::None => 0,
+            IoClass::RealTime => 1,
+            IoClass::BestEffort => 2,
+            IoClass::Idle => 3,
+        };
+        (class << IOPRIO_CLASS_SHIFT) | self.level
+    }
+
+    /// Parses the `ionice`-style forms `rt/N`, `be/N`, `idle` and `none`.
+    pub fn parse(s: &str) -> Result<IoPriority, String> {
+        let s = s.trim().to_lowercase();
+        let mut parts = s.splitn(2, '/');
+        let class = match parts.next().unwrap_or("") {
+            "rt" | "realtime" | "1" => IoClass::RealTime,
+            "be" | "best-effort" | "2" => IoClass::BestEffort,
+            "idle" | "3" => IoClass::Idle,
+            "none" | "0" => IoClass::None,
+            other => return Err(format!("unknown I/O class '{}'", other)),
+        };
+
+        let level = match (class, parts.next()) {
+            (IoClass::Idle, _) | (IoClass::None, None) => 0,
+            (_, None) => 4,
+            (_, Some(level)) => match level.parse::<i32>() {
+                Ok(level) if (0..=7).contains(&level) => level,
+                _ => return Err(String::from("I/O priority level must be 0-7")),
+            },
+        };
+
+        Ok(IoPriority { class, level })
+    }
+}
+
+/// Formats the priority the way `iotop` does. Processes without an explicit
+/// class get best-effort with a level derived from their nice value.
+pub fn format_ioprio(ioprio: Option<IoPriority>, nice: i32) -> String {
+    match ioprio {
+        Some(IoPriority {
+            class: IoClass::RealTime,
+            level,
+        }) => format!("rt/{}", level),
+        Some(IoPriority {
+            class: IoClass::BestEffort,
+            level,
+        }) => format!("be/{}", level),
+        Some(IoPriority {
+            class: IoClass::Idle,
+            ..
+        }) => String::from("idle"),
+        Some(IoPriority {
+            class: IoClass::None,
+            ..
+        }) => format!("be/{}", ((nice + 20) / 5).max(0).min(7)),
+        None => String::from("?"),
+    }
+}
+
+pub fn get_ioprio(pid: sysinfo::Pid) -> Option<IoPriority> {
+    let raw = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, pid) };
+    if raw < 0 {
+        None
+    } else {
+        Some(IoPriority::from_raw(raw as i32))
+    }
+}
+
+pub fn set_ioprio(pid: sysinfo::Pid, ioprio: IoPriority) -> io::Result<()> {
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            pid,
+            ioprio.to_raw(),
+        )
+    };
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+pub fn set_nice(pid: sysinfo::Pid, nice: i32) -> io::Result<()> {
+    let ret = unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, nice) };
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes `/proc/<pid>/oom_score_adj`. Lowering it below the current value
+/// needs `CAP_SYS_RESOURCE`.
+pub fn set_oom_score_adj(pid: sysinfo::Pid, adj: i32) -> io::Result<()> {
+    std::fs::write(format!("/proc/{}/oom_score_adj", pid), adj.to_string())
+}
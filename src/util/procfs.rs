@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The cgroup a process belongs to. On the unified (v2) hierarchy this is
+/// the `0::` entry; on v1 the first controller line is used.
+pub fn read_cgroup(pid: sysinfo::Pid) -> Option<String> {
+    let cgroups = read_cgroups(pid);
+    cgroups
+        .iter()
+        .find(|(id, _, _)| id == "0")
+        .or_else(|| cgroups.first())
+        .map(|(_, _, path)| path.clone())
+}
+
+/// Every line of `/proc/<pid>/cgroup` as (hierarchy id, controllers, path).
+pub fn read_cgroups(pid: sysinfo::Pid) -> Vec<(String, String, String)> {
+    let contents = match fs::read_to_string(format!("/proc/{}/cgroup", pid)) {
+        Ok(contents) => contents,
+        Err(_) => return vec![],
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ':');
+            Some((
+                parts.next()?.to_string(),
+                parts.next()?.to_string(),
+                parts.next()?.to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// `/proc/<pid>/environ` as name/value pairs in file order.
+pub fn read_environ(pid: sysinfo::Pid) -> io::Result<Vec<(String, String)>> {
+    let contents = fs::read(format!("/proc/{}/environ", pid))?;
+    Ok(contents
+        .split(|b| *b == 0)
+        .filter(|var| !var.is_empty())
+        .map(|var| {
+            let var = String::from_utf8_lossy(var);
+            match var.find('=') {
+                Some(i) => (var[..i].to_string(), var[i + 1..].to_string()),
+                None => (var.into_owned(), String::new()),
+            }
+        })
+        .collect())
+}
+
+/// The fields of `/proc/<pid>/stat` rstop uses.
+#[derive(Debug, Clone, Default)]
+pub struct Stat {
+    pub state: char,
+    /// User and system CPU time in clock ticks.
+    pub utime: u64,
+    pub stime: u64,
+    pub nice: i32,
+    pub num_threads: u64,
+    /// Clock ticks after boot at which the process started.
+    pub start_time: u64,
+    /// CPU the task last ran on.
+    pub processor: u32,
+}
+
+/// Parses `/proc/<pid>/stat` (or a task's `stat` given the full path). The
+/// command name is skipped by looking for the last `)` because it may
+/// itself contain spaces and parentheses.
+pub fn read_stat_file(path: &str) -> Option<Stat> {
+    let contents = fs::read_to_string(path).ok()?;
+    let rest = &contents[contents.rfind(')')? + 1..];
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+
+    // `fields[0]` is field 3 (state) in proc(5) numbering.
+    let field = |n: usize| fields.get(n - 3).copied();
+    Some(Stat {
+        state: field(3)?.chars().next()?,
+        utime: field(14)?.parse().ok()?,
+        stime: field(15)?.parse().ok()?,
+        nice: field(19)?.parse().ok()?,
+        num_threads: field(20)?.parse().ok()?,
+        start_time: field(22)?.parse().ok()?,
+        processor: field(39)?.parse().ok()?,
+    })
+}
+
+pub fn read_stat(pid: sysinfo::Pid) -> Option<Stat> {
+    read_stat_file(&format!("/proc/{}/stat", pid))
+}
+
+/// `/proc/<pid>/status` as key/value pairs, e.g. `Uid` → `1000 1000 1000 1000`.
+pub fn read_status(pid: sysinfo::Pid) -> HashMap<String, String> {
+    let mut status = HashMap::new();
+    if let Ok(contents) = fs::read_to_string(format!("/proc/{}/status", pid)) {
+        for line in contents.lines() {
+            if let Some(i) = line.find(':') {
+                status.insert(line[..i].to_string(), line[i + 1..].trim().to_string());
+            }
+        }
+    }
+    status
+}
+
+/// Resolves one of the per-process symlinks such as `exe`, `cwd` or `root`.
+pub fn read_link(pid: sysinfo::Pid, name: &str) -> Option<PathBuf> {
+    fs::read_link(format!("/proc/{}/{}", pid, name)).ok()
+}
+
+/// Boot time in seconds since the epoch, from the `btime` line of `/proc/stat`.
+pub fn boot_time() -> Option<u64> {
+    let contents = fs::read_to_string("/proc/stat").ok()?;
+    contents
+        .lines()
+        .find(|line| line.starts_with("btime "))
+        .and_then(|line| line[6..].trim().parse().ok())
+}
+
+pub fn clock_ticks() -> u64 {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 {
+        ticks as u64
+    } else {
+        100
+    }
+}
+
+/// One row of `/proc/<pid>/limits`; `None` means unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct Limit {
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+    pub units: String,
+}
+
+/// `/proc/<pid>/limits` in file order. The columns are fixed-width and the
+/// names contain spaces, so the header line gives the column offsets.
+pub fn read_limits(pid: sysinfo::Pid) -> Vec<(String, Limit)> {
+    let contents = match fs::read_to_string(format!("/proc/{}/limits", pid)) {
+        Ok(contents) => contents,
+        Err(_) => return vec![],
+    };
+    let mut lines = contents.lines();
+    let header = match lines.next() {
+        Some(header) => header,
+        None => return vec![],
+    };
+    let (soft_at, hard_at, units_at) = match (
+        header.find("Soft Limit"),
+        header.find("Hard Limit"),
+        header.find("Units"),
+    ) {
+        (Some(soft), Some(hard), Some(units)) => (soft, hard, units),
+        _ => return vec![],
+    };
+
+    let column = |line: &str, from: usize, to: usize| {
+        line.get(from..to.min(line.len()))
+            .unwrap_or("")
+            .trim()
+            .to_string()
+    };
+    let value = |s: String| s.parse().ok();
+
+    lines
+        .map(|line| {
+            let limit = Limit {
+                soft: value(column(line, soft_at, hard_at)),
+                hard: value(column(line, hard_at, units_at)),
+                units: column(line, units_at, line.len()),
+            };
+            (column(line, 0, soft_at), limit)
+        })
+        .collect()
+}
+
+/// Totals from `/proc/<pid>/smaps_rollup`, in bytes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryUsage {
+    pub rss: u64,
+    pub pss: u64,
+    pub shared_clean: u64,
+    pub shared_dirty: u64,
+    pub private_clean: u64,
+    pub private_dirty: u64,
+    pub swap: u64,
+}
+
+impl MemoryUsage {
+    /// Unique set size: the pages only this process maps.
+    pub fn uss(&self) -> u64 {
+        self.private_clean + self.private_dirty
+    }
+
+    pub fn shared(&self) -> u64 {
+        self.shared_clean + self.shared_dirty
+    }
+}
+
+/// Needs the same ptrace access as `smaps`, so this is `None` for other
+/// users' processes unless running as root.
+pub fn read_smaps_rollup(pid: sysinfo::Pid) -> Option<MemoryUsage> {
+    let contents = fs::read_to_string(format!("/proc/{}/smaps_rollup", pid)).ok()?;
+
+    let mut usage = MemoryUsage::default();
+    for line in contents.lines() {
+        let mut parts = line.split_whitespace();
+        let (key, kb) = match (
+            parts.next(),
+            parts.next().and_then(|v| v.parse::<u64>().ok()),
+        ) {
+            (Some(key), Some(kb)) => (key, kb),
+            _ => continue,
+        };
+        let field = match key {
+            "Rss:" => &mut usage.rss,
+            "Pss:" => &mut usage.pss,
+            "Shared_Clean:" => &mut usage.shared_clean,
+            "Shared_Dirty:" => &mut usage.shared_dirty,
+            "Private_Clean:" => &mut usage.private_clean,
+            "Private_Dirty:" => &mut usage.private_dirty,
+            "Swap:" => &mut usage.swap,
+            _ => continue,
+        };
+        *field = kb * 1024;
+    }
+    Some(usage)
+}
+
+/// One line of `/proc/<pid>/maps`.
+#[derive(Debug, Clone, Default)]
+pub struct Mapping {
+    pub start: u64,
+    pub end: u64,
+    pub perms: String,
+    /// File path, a pseudo name like `[heap]`, or empty when anonymous.
+    pub path: String,
+}
+
+impl Mapping {
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+pub fn read_maps(pid: sysinfo::Pid) -> Vec<Mapping> {
+    let contents = match fs::read_to_string(format!("/proc/{}/maps", pid)) {
+        Ok(contents) => contents,
+        Err(_) => return vec![],
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            // The path is the sixth field and may itself contain spaces.
+            let mut parts = line.splitn(6, ' ');
+            let range = parts.next()?;
+            let perms = parts.next()?.to_string();
+            let path = parts.nth(3).unwrap_or("").trim_start().to_string();
+
+            let mut bounds = range.split('-');
+            let start = u64::from_str_radix(bounds.next()?, 16).ok()?;
+            let end = u64::from_str_radix(bounds.next()?, 16).ok()?;
+            Some(Mapping {
+                start,
+                end,
+                perms,
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Bytes read from and written to storage according to an `io` file, such
+/// as `/proc/<pid>/task/<tid>/io`. Only readable for our own processes
+/// unless running as root.
+pub fn read_io_file(path: &str) -> Option<(u64, u64)> {
+    let contents = fs::read_to_string(path).ok()?;
+    let field = |name: &str| {
+        contents
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|value| value.trim().parse().ok())
+    };
+    Some((field("read_bytes:")?, field("write_bytes:")?))
+}
+
+/// A per-process file holding a single number, such as `oom_score`.
+pub fn read_number<T: std::str::FromStr>(pid: sysinfo::Pid, name: &str) -> Option<T> {
+    fs::read_to_string(format!("/proc/{}/{}", pid, name))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Seconds since boot, from `/proc/uptime`.
+pub fn uptime() -> Option<u64> {
+    let contents = fs::read_to_string("/proc/uptime").ok()?;
+    let secs: f64 = contents.split_whitespace().next()?.parse().ok()?;
+    Some(secs as u64)
+}
+
+pub fn hostname() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|s| s.trim().to_string())
+}
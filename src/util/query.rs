@@ -0,0 +1,639 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+
+use crate::util::{app::ProcessInfo, config};
+
+/// A parsed process filter such as `cpu > 20 and user == "postgres"`.
+///
+/// Comparisons are joined with `and`, `or` and `not` (or `&&`, `||`, `!`)
+/// and may be grouped with parentheses. `@name` splices in a saved filter.
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub text: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub column: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (column {})", self.message, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Pid,
+    Ppid,
+    Name,
+    Cmd,
+    User,
+    State,
+    Cpu,
+    Mem,
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Num(f64),
+    Str(String),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp(Field, Op, Value),
+}
+
+impl Field {
+    const NAMES: &'static str = "pid, ppid, name, cmd, user, state, cpu, mem, read, write";
+
+    fn from_name(name: &str) -> Option<Field> {
+        match name.to_lowercase().as_str() {
+            "pid" => Some(Field::Pid),
+            "ppid" | "parent" => Some(Field::Ppid),
+            "name" | "comm" => Some(Field::Name),
+            "cmd" | "command" => Some(Field::Cmd),
+            "user" => Some(Field::User),
+            "state" | "status" => Some(Field::State),
+            "cpu" => Some(Field::Cpu),
+            "mem" | "memory" => Some(Field::Mem),
+            "read" => Some(Field::Read),
+            "write" => Some(Field::Write),
+            _ => None,
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            Field::Pid | Field::Ppid | Field::Cpu | Field::Mem | Field::Read | Field::Write
+        )
+    }
+}
+
+impl Query {
+    /// Parses `text`, resolving `@name` references against `saved`.
+    pub fn parse(text: &str, saved: &BTreeMap<String, String>) -> Result<Query, ParseError> {
+        let expr = parse_expr(text, saved, 0)?;
+        Ok(Query {
+            text: text.trim().to_string(),
+            expr,
+        })
+    }
+
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        eval(&self.expr, process)
+    }
+}
+
+const MAX_NESTING: usize = 16;
+
+fn parse_expr(
+    text: &str,
+    saved: &BTreeMap<String, String>,
+    depth: usize,
+) -> Result<Expr, ParseError> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: text.chars().count() + 1,
+        saved,
+        depth,
+    };
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(tok) => Err(tok.error("expected 'and', 'or' or end of filter")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Word(String),
+    Str(String),
+    Num(f64),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+    Saved(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            column: self.column,
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-./:".contains(c)
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let err = |message: &str| ParseError {
+            message: message.to_string(),
+            column,
+        };
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        let (tok, len) = match two.as_str() {
+            "==" => (Tok::Op(Op::Eq), 2),
+            "!=" => (Tok::Op(Op::Ne), 2),
+            ">=" => (Tok::Op(Op::Ge), 2),
+            "<=" => (Tok::Op(Op::Le), 2),
+            "&&" => (Tok::And, 2),
+            "||" => (Tok::Or, 2),
+            _ => match c {
+                '=' => (Tok::Op(Op::Eq), 1),
+                '>' => (Tok::Op(Op::Gt), 1),
+                '<' => (Tok::Op(Op::Lt), 1),
+                '~' => (Tok::Op(Op::Contains), 1),
+                '!' => (Tok::Not, 1),
+                '(' => (Tok::LParen, 1),
+                ')' => (Tok::RParen, 1),
+                '"' | '\'' => {
+                    let mut s = String::new();
+                    let mut j = i + 1;
+                    while j < chars.len() && chars[j] != c {
+                        if chars[j] == '\\' && j + 1 < chars.len() {
+                            j += 1;
+                        }
+                        s.push(chars[j]);
+                        j += 1;
+                    }
+                    if j >= chars.len() {
+                        return Err(err("unterminated string"));
+                    }
+                    (Tok::Str(s), j + 1 - i)
+                }
+                '@' => {
+                    let mut j = i + 1;
+                    while j < chars.len() && is_word_char(chars[j]) {
+                        j += 1;
+                    }
+                    if j == i + 1 {
+                        return Err(err("expected a saved filter name after '@'"));
+                    }
+                    (Tok::Saved(chars[i + 1..j].iter().collect()), j - i)
+                }
+                c if c.is_ascii_digit()
+                    || (c == '-' && chars.get(i + 1).map_or(false, |c| c.is_ascii_digit())) =>
+                {
+                    let mut j = i + 1;
+                    while j < chars.len() && (chars[j].is_ascii_digit() || chars[j] == '.') {
+                        j += 1;
+                    }
+                    let number: String = chars[i..j].iter().collect();
+                    let mut value: f64 = number.parse().map_err(|_| err("invalid number"))?;
+
+                    let mut k = j;
+                    while k < chars.len() && (chars[k].is_alphabetic() || chars[k] == '%') {
+                        k += 1;
+                    }
+                    let suffix: String = chars[j..k].iter().collect();
+                    value *= match suffix.to_lowercase().as_str() {
+                        "" | "%" | "b" => 1.0,
+                        "k" | "kb" | "kib" => 1024.0,
+                        "m" | "mb" | "mib" => 1024.0 * 1024.0,
+                        "g" | "gb" | "gib" => 1024.0 * 1024.0 * 1024.0,
+                        "t" | "tb" | "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+                        _ => return Err(err("unknown unit suffix")),
+                    };
+                    (Tok::Num(value), k - i)
+                }
+                c if is_word_char(c) => {
+                    let mut j = i;
+                    while j < chars.len() && is_word_char(chars[j]) {
+                        j += 1;
+                    }
+                    let word: String = chars[i..j].iter().collect();
+                    let tok = match word.to_lowercase().as_str() {
+                        "and" => Tok::And,
+                        "or" => Tok::Or,
+                        "not" => Tok::Not,
+                        _ => Tok::Word(word),
+                    };
+                    (tok, j - i)
+                }
+                _ => return Err(err(&format!("unexpected character '{}'", c))),
+            },
+        };
+
+        tokens.push(Token { tok, column });
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    end: usize,
+    saved: &'a BTreeMap<String, String>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn eof(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            column: self.end,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.and()?;
+        while self.peek().map(|t| &t.tok) == Some(&Tok::Or) {
+            self.next();
+            let rhs = self.and()?;
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.not()?;
+        while self.peek().map(|t| &t.tok) == Some(&Tok::And) {
+            self.next();
+            let rhs = self.not()?;
+            lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn not(&mut self) -> Result<Expr, ParseError> {
+        if self.peek().map(|t| &t.tok) == Some(&Tok::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Err(self.eof("expected a comparison")),
+        };
+
+        match &token.tok {
+            Tok::LParen => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token {
+                        tok: Tok::RParen, ..
+                    }) => Ok(expr),
+                    Some(other) => Err(other.error("expected ')'")),
+                    None => Err(self.eof("expected ')'")),
+                }
+            }
+            Tok::Saved(name) => {
+                let text = match self.saved.get(name) {
+                    Some(text) => text,
+                    None => return Err(token.error(&format!("no saved filter named '{}'", name))),
+                };
+                if self.depth >= MAX_NESTING {
+                    return Err(token.error("saved filters nest too deeply"));
+                }
+                // Report problems inside a saved filter once, at the point
+                // where the outermost reference to it appears.
+                let depth = self.depth;
+                parse_expr(text, self.saved, depth + 1).map_err(|e| {
+                    if depth == 0 {
+                        token.error(&format!("in @{}: {}", name, e.message))
+                    } else {
+                        e
+                    }
+                })
+            }
+            Tok::Word(word) => {
+                let field = match Field::from_name(word) {
+                    Some(field) => field,
+                    None => {
+                        return Err(token.error(&format!(
+                            "unknown field '{}', expected one of {}",
+                            word,
+                            Field::NAMES
+                        )))
+                    }
+                };
+                self.comparison(field)
+            }
+            _ => Err(token.error("expected a field name")),
+        }
+    }
+
+    fn comparison(&mut self, field: Field) -> Result<Expr, ParseError> {
+        let op = match self.next() {
+            Some(Token {
+                tok: Tok::Op(op), ..
+            }) => op,
+            Some(other) => return Err(other.error("expected a comparison operator")),
+            None => return Err(self.eof("expected a comparison operator")),
+        };
+
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Err(self.eof("expected a value")),
+        };
+        let value = match token.tok {
+            Tok::Num(n) => Value::Num(n),
+            Tok::Str(ref s) | Tok::Word(ref s) => Value::Str(s.clone()),
+            _ => return Err(token.error("expected a value")),
+        };
+
+        if field.is_numeric() {
+            match (&value, op) {
+                (_, Op::Contains) => {
+                    return Err(token.error("'~' only applies to text fields"));
+                }
+                (Value::Str(_), _) => return Err(token.error("expected a number")),
+                _ => {}
+            }
+        } else {
+            match op {
+                Op::Gt | Op::Ge | Op::Lt | Op::Le => {
+                    return Err(token.error("ordering only applies to numeric fields"));
+                }
+                _ => {}
+            }
+        }
+
+        let value = match value {
+            Value::Num(n) if !field.is_numeric() => Value::Str(format_number(n)),
+            value => value,
+        };
+
+        Ok(Expr::Cmp(field, op, value))
+    }
+}
+
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+fn eval(expr: &Expr, p: &ProcessInfo) -> bool {
+    match expr {
+        Expr::And(a, b) => eval(a, p) && eval(b, p),
+        Expr::Or(a, b) => eval(a, p) || eval(b, p),
+        Expr::Not(a) => !eval(a, p),
+        Expr::Cmp(field, op, Value::Num(rhs)) => {
+            let lhs = match field {
+                Field::Pid => p.pid as f64,
+                Field::Ppid => match p.parent {
+                    Some(parent) => parent as f64,
+                    None => return false,
+                },
+                Field::Cpu => p.cpu as f64,
+                Field::Mem => p.mem as f64,
+                Field::Read => p.read_rate as f64,
+                Field::Write => p.write_rate as f64,
+                _ => return false,
+            };
+            match op {
+                Op::Eq => (lhs - rhs).abs() < f64::EPSILON,
+                Op::Ne => (lhs - rhs).abs() >= f64::EPSILON,
+                Op::Gt => lhs > *rhs,
+                Op::Ge => lhs >= *rhs,
+                Op::Lt => lhs < *rhs,
+                Op::Le => lhs <= *rhs,
+                Op::Contains => false,
+            }
+        }
+        Expr::Cmp(field, op, Value::Str(rhs)) => {
+            let state;
+            let lhs: &str = match field {
+                Field::Name => &p.name,
+                Field::Cmd => &p.cmd,
+                Field::User => &p.user,
+                Field::State => {
+                    state = p.state.to_string();
+                    &state
+                }
+                _ => return false,
+            };
+            match op {
+                Op::Eq => lhs == rhs,
+                Op::Ne => lhs != rhs,
+                Op::Contains => lhs.to_lowercase().contains(&rhs.to_lowercase()),
+                _ => false,
+            }
+        }
+    }
+}
+
+/// Whether `name` can be saved: it has to survive the `name = expression`
+/// lines of the filters file and be typed after `@`.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Reads saved filters, one `name = expression` per line.
+pub fn load_saved_filters() -> BTreeMap<String, String> {
+    let mut saved = BTreeMap::new();
+    let contents = match config::config_path("filters").map(fs::read_to_string) {
+        Some(Ok(contents)) => contents,
+        _ => return saved,
+    };
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(i) = line.find('=') {
+            let name = line[..i].trim();
+            let expr = line[i + 1..].trim();
+            if !name.is_empty() && !expr.is_empty() {
+                saved.insert(name.to_string(), expr.to_string());
+            }
+        }
+    }
+
+    saved
+}
+
+pub fn store_saved_filters(saved: &BTreeMap<String, String>) -> io::Result<()> {
+    let path = config::config_path("filters")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut contents = String::new();
+    for (name, expr) in saved {
+        contents += &format!("{} = {}\n", name, expr);
+    }
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Query, ParseError> {
+        Query::parse(text, &BTreeMap::new())
+    }
+
+    fn process(pid: sysinfo::Pid, name: &str, cpu: f32) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: name.to_string(),
+            cpu,
+            ..ProcessInfo::default()
+        }
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let q = parse("name == a or name == b and cpu > 50").unwrap();
+        assert!(q.matches(&process(1, "a", 0.0)));
+        assert!(!q.matches(&process(1, "b", 0.0)));
+        assert!(q.matches(&process(1, "b", 60.0)));
+
+        let q = parse("(name == a or name == b) and cpu > 50").unwrap();
+        assert!(!q.matches(&process(1, "a", 0.0)));
+        assert!(q.matches(&process(1, "a", 60.0)));
+    }
+
+    #[test]
+    fn not_applies_to_one_comparison() {
+        let q = parse("not name == a and cpu > 50").unwrap();
+        assert!(q.matches(&process(1, "b", 60.0)));
+        assert!(!q.matches(&process(1, "a", 60.0)));
+        assert!(!q.matches(&process(1, "b", 0.0)));
+    }
+
+    #[test]
+    fn quoted_strings() {
+        let q = parse(r#"name == "a b" or name == 'it\'s'"#).unwrap();
+        assert!(q.matches(&process(1, "a b", 0.0)));
+        assert!(q.matches(&process(1, "it's", 0.0)));
+        assert!(!q.matches(&process(1, "a", 0.0)));
+
+        let q = parse(r#"name == "and""#).unwrap();
+        assert!(q.matches(&process(1, "and", 0.0)));
+    }
+
+    #[test]
+    fn negative_numbers() {
+        let q = parse("cpu > -1").unwrap();
+        assert!(q.matches(&process(1, "a", 0.0)));
+
+        let q = parse("pid != -5").unwrap();
+        assert!(q.matches(&process(5, "a", 0.0)));
+    }
+
+    #[test]
+    fn unit_suffixes() {
+        let q = parse("mem >= 2M").unwrap();
+        let mut p = process(1, "a", 0.0);
+        p.mem = 2 * 1024 * 1024;
+        assert!(q.matches(&p));
+        p.mem -= 1;
+        assert!(!q.matches(&p));
+    }
+
+    #[test]
+    fn bad_input() {
+        let column = |text: &str| parse(text).unwrap_err().column;
+
+        assert_eq!(column("bogus > 1"), 1);
+        assert_eq!(column("cpu >"), 6);
+        assert_eq!(column("cpu > 1 and"), 12);
+        assert_eq!(column("name == \"abc"), 9);
+        assert_eq!(column("(cpu > 1"), 9);
+        assert_eq!(column("cpu > 1)"), 8);
+        assert_eq!(column("cpu ~ 1"), 7);
+        assert_eq!(column("name > a"), 8);
+        assert_eq!(column("cpu > abc"), 7);
+        assert_eq!(column("cpu > 1q"), 7);
+        assert_eq!(column("cpu # 1"), 5);
+    }
+
+    #[test]
+    fn saved_filters() {
+        let mut saved = BTreeMap::new();
+        saved.insert(String::from("busy"), String::from("cpu > 50"));
+        saved.insert(String::from("loop"), String::from("@loop"));
+
+        let q = Query::parse("@busy and name == a", &saved).unwrap();
+        assert!(q.matches(&process(1, "a", 60.0)));
+        assert!(!q.matches(&process(1, "a", 0.0)));
+
+        assert!(Query::parse("@missing", &saved).is_err());
+        assert!(Query::parse("@loop", &saved).is_err());
+    }
+
+    #[test]
+    fn filter_names() {
+        assert!(is_valid_name("busy-web_2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("a=b"));
+        assert!(!is_valid_name("a b"));
+        assert!(!is_valid_name("a\nb"));
+    }
+}
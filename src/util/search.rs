@@ -0,0 +1,115 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::util::app::ProcessInfo;
+
+/// The `/` filter of the process table. Matching is case-insensitive and
+/// runs against both the process name and its full command line.
+#[derive(Debug, Default)]
+pub struct Search {
+    pub query: String,
+    pub regex: bool,
+    compiled: Option<Regex>,
+    pub error: Option<String>,
+}
+
+impl Search {
+    pub fn is_active(&self) -> bool {
+        !self.query.is_empty()
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.compile();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.compile();
+    }
+
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.compile();
+    }
+
+    pub fn toggle_regex(&mut self) {
+        self.regex = !self.regex;
+        self.compile();
+    }
+
+    fn compile(&mut self) {
+        self.compiled = None;
+        self.error = None;
+        if !self.regex || self.query.is_empty() {
+            return;
+        }
+
+        match RegexBuilder::new(&self.query)
+            .case_insensitive(true)
+            .build()
+        {
+            Ok(re) => self.compiled = Some(re),
+            Err(_) => self.error = Some(String::from("invalid regex")),
+        }
+    }
+
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        self.matches_str(&process.name) || self.matches_str(&process.cmd)
+    }
+
+    fn matches_str(&self, s: &str) -> bool {
+        if self.regex {
+            match &self.compiled {
+                Some(re) => re.is_match(s),
+                None => false,
+            }
+        } else {
+            s.to_lowercase().contains(&self.query.to_lowercase())
+        }
+    }
+
+    /// Byte ranges of `s` that matched, used to highlight the name cell.
+    pub fn match_ranges(&self, s: &str) -> Vec<(usize, usize)> {
+        if !self.is_active() {
+            return vec![];
+        }
+
+        if self.regex {
+            return match &self.compiled {
+                Some(re) => re
+                    .find_iter(s)
+                    .filter(|m| m.start() < m.end())
+                    .map(|m| (m.start(), m.end()))
+                    .collect(),
+                None => vec![],
+            };
+        }
+
+        // Lowercasing can change byte lengths outside ASCII, in which case
+        // the offsets would not line up with `s`; skip highlighting then.
+        let haystack = s.to_lowercase();
+        let needle = self.query.to_lowercase();
+        if haystack.len() != s.len() {
+            return vec![];
+        }
+        haystack
+            .match_indices(&needle)
+            .map(|(i, m)| (i, i + m.len()))
+            .collect()
+    }
+
+    /// Text shown in the process table title while a filter is set.
+    pub fn describe(&self) -> String {
+        let mut s = format!("/{}", self.query);
+        if self.regex {
+            s = format!("re{}", s);
+        }
+        if let Some(err) = &self.error {
+            s = format!("{} ({})", s, err);
+        }
+        s
+    }
+}
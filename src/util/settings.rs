@@ -0,0 +1,518 @@
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tui::style::Color;
+
+use crate::util::{
+    cli, config,
+    keymap::{Keymap, KeysEntry},
+    theme::{ColorDepth, Theme},
+    units::{ByteUnits, Temperature},
+};
+
+/// The panels of the main screen, named as in the `widgets` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Widget {
+    Cpu,
+    Disk,
+    Temp,
+    Mem,
+    Net,
+    Proc,
+    Battery,
+}
+
+impl Widget {
+    const DEFAULT: [Widget; 6] = [
+        Widget::Cpu,
+        Widget::Disk,
+        Widget::Temp,
+        Widget::Mem,
+        Widget::Net,
+        Widget::Proc,
+    ];
+
+    const MINIMAL: [Widget; 3] = [Widget::Cpu, Widget::Mem, Widget::Proc];
+
+    /// Also accepts gotop's names, as used in its layout files.
+    fn parse(name: &str) -> Result<Widget, String> {
+        match name.trim() {
+            "cpu" => Ok(Widget::Cpu),
+            "disk" => Ok(Widget::Disk),
+            "temp" => Ok(Widget::Temp),
+            "mem" => Ok(Widget::Mem),
+            "net" => Ok(Widget::Net),
+            "proc" | "procs" => Ok(Widget::Proc),
+            "battery" | "batt" => Ok(Widget::Battery),
+            other => Err(format!(
+                "unknown widget `{}` (expected cpu, disk, temp, mem, net, proc or battery)",
+                other
+            )),
+        }
+    }
+}
+
+/// The widgets of a named layout, or of a gotop layout file. Those files
+/// arrange widgets in rows with optional `weight:` prefixes and `/height`
+/// suffixes, as in `2:cpu/2`; rstop keeps its own arrangement and only takes
+/// which widgets are listed.
+fn read_layout(name: &str) -> Result<Vec<Widget>, String> {
+    let mut widgets = match name {
+        "default" => return Ok(Widget::DEFAULT.to_vec()),
+        "minimal" => return Ok(Widget::MINIMAL.to_vec()),
+        "battery" | "kitchensink" => {
+            let mut widgets = Widget::DEFAULT.to_vec();
+            widgets.push(Widget::Battery);
+            return Ok(widgets);
+        }
+        "procs" => return Ok(vec![Widget::Proc]),
+        _ => vec![],
+    };
+
+    let contents = fs::read_to_string(name).map_err(|err| {
+        format!(
+            "no layout named {} (expected default, minimal, battery, kitchensink, procs or a file): {}",
+            name, err
+        )
+    })?;
+    for word in contents.split_whitespace() {
+        let word = word.rsplit(':').next().unwrap_or(word);
+        let word = word.split('/').next().unwrap_or(word);
+        let widget = Widget::parse(word).map_err(|err| format!("{}: {}", name, err))?;
+        if !widgets.contains(&widget) {
+            widgets.push(widget);
+        }
+    }
+    Ok(widgets)
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(format!("expected true or false, got {}", value)),
+    }
+}
+
+/// A colour as written in the config file: a name such as `cyan` or
+/// `light-red`, a 256-colour index, or `#rrggbb`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ColorValue(pub Color);
+
+impl TryFrom<String> for ColorValue {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<ColorValue, String> {
+        parse_color(&s).map(ColorValue)
+    }
+}
+
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    let name = s.trim().to_lowercase().replace('_', "-");
+    let color = match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "dark-gray" | "dark-grey" => Color::DarkGray,
+        "light-red" => Color::LightRed,
+        "light-green" => Color::LightGreen,
+        "light-yellow" => Color::LightYellow,
+        "light-blue" => Color::LightBlue,
+        "light-magenta" => Color::LightMagenta,
+        "light-cyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => {
+            if let Some(hex) = name.strip_prefix('#') {
+                let rgb = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6);
+                match rgb {
+                    Some(rgb) => Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8),
+                    None => return Err(format!("bad colour `{}` (expected #rrggbb)", s)),
+                }
+            } else if let Ok(index) = name.parse::<u8>() {
+                Color::Indexed(index)
+            } else {
+                return Err(format!("unknown colour `{}`", s));
+            }
+        }
+    };
+    Ok(color)
+}
+
+/// Colours replacing those of a theme, as in `[colors]` and
+/// `[themes.<name>]`; unset ones are kept.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    /// The scheme a `[themes.<name>]` table starts from, `default` if unset.
+    pub base: Option<String>,
+    pub fg: Option<ColorValue>,
+    pub bg: Option<ColorValue>,
+    pub cpu: Option<Vec<ColorValue>>,
+    pub memory: Option<ColorValue>,
+    pub swap: Option<ColorValue>,
+    pub rx: Option<ColorValue>,
+    pub tx: Option<ColorValue>,
+    pub title: Option<ColorValue>,
+    pub border: Option<ColorValue>,
+    pub header: Option<ColorValue>,
+    pub selected: Option<ColorValue>,
+    pub text: Option<ColorValue>,
+    pub dim: Option<ColorValue>,
+    pub axis: Option<ColorValue>,
+    pub tagged: Option<ColorValue>,
+    pub good: Option<ColorValue>,
+    pub warn: Option<ColorValue>,
+    pub error: Option<ColorValue>,
+}
+
+impl Colors {
+    fn apply(&self, theme: &mut Theme) -> Result<(), String> {
+        if let Some(cpu) = &self.cpu {
+            if cpu.is_empty() {
+                return Err(String::from("cpu must list at least one colour"));
+            }
+            theme.cpu = cpu.iter().map(|c| c.0).collect();
+        }
+        let mut slots = [
+            (&self.fg, &mut theme.fg),
+            (&self.bg, &mut theme.bg),
+            (&self.memory, &mut theme.memory),
+            (&self.swap, &mut theme.swap),
+            (&self.rx, &mut theme.rx),
+            (&self.tx, &mut theme.tx),
+            (&self.title, &mut theme.title),
+            (&self.border, &mut theme.border),
+            (&self.header, &mut theme.header),
+            (&self.selected, &mut theme.selected),
+            (&self.text, &mut theme.text),
+            (&self.dim, &mut theme.dim),
+            (&self.axis, &mut theme.axis),
+            (&self.tagged, &mut theme.tagged),
+            (&self.good, &mut theme.good),
+            (&self.warn, &mut theme.warn),
+            (&self.error, &mut theme.error),
+        ];
+        for (value, slot) in slots.iter_mut() {
+            if let Some(value) = value {
+                **slot = value.0;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Units {
+    pub temperature: Temperature,
+    pub bytes: ByteUnits,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units {
+            temperature: Temperature::Celsius,
+            bytes: ByteUnits::Binary,
+        }
+    }
+}
+
+/// Everything read from `config.toml`. Defaults are overridden by the file,
+/// then by `RSTOP_*` environment variables, then by command-line flags.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Milliseconds between refreshes.
+    pub tick_rate: u64,
+    /// Samples kept for the charts.
+    pub history: usize,
+    /// Shown panels; the others give their space to their neighbours.
+    pub widgets: Vec<Widget>,
+    /// A named layout or gotop layout file; replaces `widgets` when set.
+    pub layout: Option<String>,
+    /// One line for the average of all CPUs instead of one per CPU.
+    pub average_cpu: bool,
+    /// Hostname, time and uptime on the top line.
+    pub statusbar: bool,
+    /// A built-in scheme, a `[themes.<name>]` table, or a gotop colorscheme
+    /// file.
+    pub colorscheme: String,
+    /// Tweaks on top of the colorscheme.
+    pub colors: Colors,
+    pub themes: BTreeMap<String, Colors>,
+    /// `auto` detects it from `NO_COLOR`, `COLORTERM` and `TERM`; `mono`
+    /// turns colours off.
+    pub color_depth: ColorDepth,
+    pub units: Units,
+    /// Action name to key sequences, e.g. `quit = "x"` or
+    /// `signal = ["d d", "f9"]`, with `[keys.<context>]` tables for the
+    /// overlays.
+    pub keys: BTreeMap<String, KeysEntry>,
+    /// Milliseconds a partly typed key sequence or count is kept.
+    pub key_timeout: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            tick_rate: 250,
+            history: 20,
+            widgets: Widget::DEFAULT.to_vec(),
+            layout: None,
+            average_cpu: false,
+            statusbar: false,
+            colorscheme: String::from("default"),
+            colors: Colors::default(),
+            themes: BTreeMap::new(),
+            color_depth: ColorDepth::Auto,
+            units: Units::default(),
+            keys: BTreeMap::new(),
+            key_timeout: 1000,
+        }
+    }
+}
+
+/// Settings that can also be given in the environment as `RSTOP_<NAME>`.
+const FROM_ENV: [&str; 11] = [
+    "tick_rate",
+    "history",
+    "widgets",
+    "layout",
+    "average_cpu",
+    "statusbar",
+    "battery",
+    "colorscheme",
+    "color_depth",
+    "temperature",
+    "bytes",
+];
+
+impl Settings {
+    /// Reads the config file and applies the environment and then the
+    /// command-line `overrides` on top, and builds the keymap and theme from
+    /// it. Every problem found is reported, one per line.
+    pub fn load(overrides: &[(String, String)]) -> Result<(Settings, Keymap, Theme), String> {
+        let mut errors = vec![];
+
+        let path = overrides
+            .iter()
+            .find(|(name, _)| name == "config")
+            .map(|(_, value)| PathBuf::from(value))
+            .or_else(|| std::env::var_os("RSTOP_CONFIG").map(PathBuf::from));
+        let explicit = path.is_some();
+        let path = path.or_else(|| config::config_path("config.toml"));
+
+        let mut settings = match path {
+            Some(path) => match fs::read_to_string(&path) {
+                Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
+                    errors.push(format!("{}: {}", path.display(), err));
+                    Settings::default()
+                }),
+                Err(err) if err.kind() == io::ErrorKind::NotFound && !explicit => {
+                    Settings::default()
+                }
+                Err(err) => {
+                    errors.push(format!("{}: {}", path.display(), err));
+                    Settings::default()
+                }
+            },
+            None => Settings::default(),
+        };
+        if let Some(layout) = settings.layout.clone() {
+            if let Err(err) = settings.set("layout", &layout) {
+                errors.push(format!("layout: {}", err));
+            }
+        }
+
+        for name in FROM_ENV.iter() {
+            let var = format!("RSTOP_{}", name.to_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                if let Err(err) = settings.set(name, &value) {
+                    errors.push(format!("{}: {}", var, err));
+                }
+            }
+        }
+        for (name, value) in overrides.iter().filter(|(name, _)| name != "config") {
+            if let Err(err) = settings.set(name, value) {
+                errors.push(format!("{}: {}", cli::flag_name(name), err));
+            }
+        }
+
+        errors.extend(settings.validate());
+        let keymap = settings
+            .keymap()
+            .map_err(|keymap_errors| errors.extend(keymap_errors))
+            .ok();
+        let theme = settings.theme().map_err(|err| errors.push(err)).ok();
+        match (keymap, theme) {
+            (Some(keymap), Some(theme)) if errors.is_empty() => Ok((settings, keymap, theme)),
+            _ => Err(errors.join("\n")),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match name {
+            "tick_rate" => {
+                self.tick_rate = value
+                    .parse()
+                    .map_err(|_| format!("not a number of milliseconds: {}", value))?
+            }
+            "history" => {
+                self.history = value
+                    .parse()
+                    .map_err(|_| format!("not a number: {}", value))?
+            }
+            "widgets" => {
+                self.widgets = value
+                    .split(',')
+                    .filter(|w| !w.trim().is_empty())
+                    .map(Widget::parse)
+                    .collect::<Result<_, _>>()?
+            }
+            "rate" => {
+                // gotop's rate is updates per second.
+                let rate: f64 = value
+                    .parse()
+                    .ok()
+                    .filter(|rate: &f64| *rate > 0.0)
+                    .ok_or_else(|| {
+                        format!("not a positive number of updates per second: {}", value)
+                    })?;
+                self.tick_rate = (1000.0 / rate).round() as u64;
+            }
+            "layout" => {
+                self.widgets = read_layout(value)?;
+                self.layout = Some(value.to_string());
+            }
+            "minimal" => {
+                if parse_bool(value)? {
+                    self.widgets = Widget::MINIMAL.to_vec();
+                }
+            }
+            "battery" => {
+                self.widgets.retain(|w| *w != Widget::Battery);
+                if parse_bool(value)? {
+                    self.widgets.push(Widget::Battery);
+                }
+            }
+            "average_cpu" => self.average_cpu = parse_bool(value)?,
+            "statusbar" => self.statusbar = parse_bool(value)?,
+            "colorscheme" => self.colorscheme = value.to_string(),
+            "color_depth" => self.color_depth = ColorDepth::parse(value)?,
+            "no_color" => {
+                if parse_bool(value)? {
+                    self.color_depth = ColorDepth::Mono;
+                }
+            }
+            "fahrenheit" => {
+                if parse_bool(value)? {
+                    self.units.temperature = Temperature::Fahrenheit;
+                }
+            }
+            "temperature" => {
+                self.units.temperature = match value {
+                    "celsius" | "c" => Temperature::Celsius,
+                    "fahrenheit" | "f" => Temperature::Fahrenheit,
+                    _ => return Err(format!("expected celsius or fahrenheit, got {}", value)),
+                }
+            }
+            "bytes" => {
+                self.units.bytes = match value {
+                    "binary" => ByteUnits::Binary,
+                    "decimal" => ByteUnits::Decimal,
+                    _ => return Err(format!("expected binary or decimal, got {}", value)),
+                }
+            }
+            _ => return Err(format!("unknown setting {}", name)),
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+
+        if !(50..=60_000).contains(&self.tick_rate) {
+            errors.push(format!(
+                "tick_rate must be between 50 and 60000 ms, got {}",
+                self.tick_rate
+            ));
+        }
+        if !(2..=1000).contains(&self.history) {
+            errors.push(format!(
+                "history must be between 2 and 1000 samples, got {}",
+                self.history
+            ));
+        }
+        if self.widgets.is_empty() {
+            errors.push(String::from("widgets must name at least one widget"));
+        }
+        let mut seen = HashSet::new();
+        for widget in &self.widgets {
+            if !seen.insert(widget) {
+                errors.push(format!("widget {:?} is listed twice", widget));
+            }
+        }
+        if self.colors.base.is_some() {
+            errors.push(String::from(
+                "colors.base is only for [themes.<name>]; set colorscheme instead",
+            ));
+        }
+
+        errors
+    }
+
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_rate)
+    }
+
+    pub fn shows(&self, widget: Widget) -> bool {
+        self.widgets.contains(&widget)
+    }
+
+    /// The colorscheme with `[colors]` applied, in the colours the terminal
+    /// can show.
+    fn theme(&self) -> Result<Theme, String> {
+        let mut theme = self.scheme(&self.colorscheme, &mut vec![])?;
+        self.colors
+            .apply(&mut theme)
+            .map_err(|err| format!("colors.{}", err))?;
+        theme.degrade(self.color_depth);
+        Ok(theme)
+    }
+
+    /// `seen` holds the custom themes being resolved, to catch loops.
+    fn scheme(&self, name: &str, seen: &mut Vec<String>) -> Result<Theme, String> {
+        let custom = match self.themes.get(name) {
+            Some(custom) => custom,
+            None => return Theme::load(name),
+        };
+        if seen.iter().any(|s| s == name) {
+            return Err(format!("themes.{} is based on itself", name));
+        }
+        seen.push(name.to_string());
+
+        let mut theme = self.scheme(custom.base.as_deref().unwrap_or("default"), seen)?;
+        custom
+            .apply(&mut theme)
+            .map_err(|err| format!("themes.{}.{}", name, err))?;
+        Ok(theme)
+    }
+
+    fn keymap(&self) -> Result<Keymap, Vec<String>> {
+        Keymap::build(&self.keys, Duration::from_millis(self.key_timeout))
+    }
+}
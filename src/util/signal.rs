@@ -0,0 +1,23 @@
+use std::io;
+
+/// Signals offered by the signal picker, in the order they are listed.
+pub const SIGNALS: [(&str, libc::c_int); 8] = [
+    ("SIGTERM", libc::SIGTERM),
+    ("SIGKILL", libc::SIGKILL),
+    ("SIGHUP", libc::SIGHUP),
+    ("SIGINT", libc::SIGINT),
+    ("SIGSTOP", libc::SIGSTOP),
+    ("SIGCONT", libc::SIGCONT),
+    ("SIGUSR1", libc::SIGUSR1),
+    ("SIGUSR2", libc::SIGUSR2),
+];
+
+/// Sends `signal` to `pid`, returning the OS error (e.g. EPERM, ESRCH) if
+/// the kernel refuses.
+pub fn send(pid: sysinfo::Pid, signal: libc::c_int) -> io::Result<()> {
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
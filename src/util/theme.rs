@@ -0,0 +1,709 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use tui::style::{Color, Modifier, Style};
+
+use crate::util::config;
+
+/// The colours every widget is drawn with.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Base text and background, `Reset` for the terminal's own.
+    pub fg: Color,
+    pub bg: Color,
+    /// One per CPU line, reused when there are more CPUs.
+    pub cpu: Vec<Color>,
+    pub memory: Color,
+    pub swap: Color,
+    pub rx: Color,
+    pub tx: Color,
+    /// Widget titles and the statusbar.
+    pub title: Color,
+    pub border: Color,
+    /// Table headers, labels and prompts.
+    pub header: Color,
+    /// The cursor in the process table and pickers.
+    pub selected: Color,
+    /// Process rows.
+    pub text: Color,
+    /// Hints, unbound keys and processes that don't match the search.
+    pub dim: Color,
+    /// Chart axes and inactive tabs.
+    pub axis: Color,
+    pub tagged: Color,
+    /// New processes and spawn events.
+    pub good: Color,
+    /// Pinned processes, deleted libraries and search matches.
+    pub warn: Color,
+    /// Errors, status messages, exits and deleted executables.
+    pub error: Color,
+    /// Colours are off; the `*_style` methods use bold, reverse, underline
+    /// and dim instead.
+    pub monochrome: bool,
+}
+
+/// How many colours the terminal can show, as in the `color_depth` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum ColorDepth {
+    /// Detected from the environment.
+    Auto,
+    TrueColor,
+    Indexed,
+    Basic,
+    Mono,
+}
+
+impl TryFrom<String> for ColorDepth {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<ColorDepth, String> {
+        ColorDepth::parse(&s)
+    }
+}
+
+impl ColorDepth {
+    pub fn parse(s: &str) -> Result<ColorDepth, String> {
+        match s.trim() {
+            "auto" => Ok(ColorDepth::Auto),
+            "truecolor" | "24bit" => Ok(ColorDepth::TrueColor),
+            "256" => Ok(ColorDepth::Indexed),
+            "16" => Ok(ColorDepth::Basic),
+            "mono" | "none" => Ok(ColorDepth::Mono),
+            other => Err(format!(
+                "expected auto, truecolor, 256, 16 or mono, got {}",
+                other
+            )),
+        }
+    }
+
+    /// `NO_COLOR` turns colours off; otherwise `COLORTERM` and `TERM` tell.
+    /// Unknown terminals get 16 colours, which serial consoles and screen
+    /// can show.
+    pub fn detect() -> ColorDepth {
+        if std::env::var_os("NO_COLOR").map_or(false, |v| !v.is_empty()) {
+            return ColorDepth::Mono;
+        }
+        let term = std::env::var("TERM").unwrap_or_default();
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+
+        if term == "dumb" || term.starts_with("vt1") || term.starts_with("vt2") {
+            ColorDepth::Mono
+        } else if term.contains("direct")
+            || term.contains("truecolor")
+            // screen passes COLORTERM through but can't show 24-bit colour.
+            || ((colorterm == "truecolor" || colorterm == "24bit") && !term.starts_with("screen"))
+        {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Indexed
+        } else {
+            ColorDepth::Basic
+        }
+    }
+}
+
+/// The built-in colour schemes, for `colorscheme` and `-c`.
+pub const BUILTIN: [&str; 6] = [
+    "default",
+    "monokai",
+    "solarized",
+    "nord",
+    "light-background",
+    "monochrome",
+];
+
+impl Theme {
+    pub fn cpu(&self, i: usize) -> Color {
+        self.cpu[i % self.cpu.len()]
+    }
+
+    pub fn header_style(&self) -> Style {
+        self.emphasis(self.header, Modifier::BOLD)
+    }
+
+    pub fn selected_style(&self) -> Style {
+        self.emphasis(self.selected, Modifier::REVERSED)
+    }
+
+    pub fn dim_style(&self) -> Style {
+        self.emphasis(self.dim, Modifier::DIM)
+    }
+
+    pub fn tagged_style(&self) -> Style {
+        self.emphasis(self.tagged, Modifier::BOLD)
+    }
+
+    pub fn good_style(&self) -> Style {
+        self.emphasis(self.good, Modifier::BOLD)
+    }
+
+    pub fn warn_style(&self) -> Style {
+        self.emphasis(self.warn, Modifier::UNDERLINED)
+    }
+
+    pub fn error_style(&self) -> Style {
+        self.emphasis(self.error, Modifier::BOLD)
+    }
+
+    fn emphasis(&self, color: Color, modifier: Modifier) -> Style {
+        let style = Style::default().fg(color);
+        if self.monochrome {
+            style.add_modifier(modifier)
+        } else {
+            style
+        }
+    }
+
+    /// Replaces every colour with the closest one `depth` allows; `Mono`
+    /// drops them all.
+    pub fn degrade(&mut self, depth: ColorDepth) {
+        let depth = match depth {
+            ColorDepth::Auto => ColorDepth::detect(),
+            depth => depth,
+        };
+        self.monochrome = depth == ColorDepth::Mono;
+        let slots = self.cpu.iter_mut().chain(vec![
+            &mut self.fg,
+            &mut self.bg,
+            &mut self.memory,
+            &mut self.swap,
+            &mut self.rx,
+            &mut self.tx,
+            &mut self.title,
+            &mut self.border,
+            &mut self.header,
+            &mut self.selected,
+            &mut self.text,
+            &mut self.dim,
+            &mut self.axis,
+            &mut self.tagged,
+            &mut self.good,
+            &mut self.warn,
+            &mut self.error,
+        ]);
+        for slot in slots {
+            *slot = degrade(*slot, depth);
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let theme = match name {
+            "default" => Theme::default(),
+            "monokai" => Theme {
+                fg: Color::Indexed(249),
+                cpu: indexed(&[81, 70, 208, 197, 249, 141, 221, 186]),
+                memory: Color::Indexed(208),
+                swap: Color::Indexed(186),
+                rx: Color::Indexed(81),
+                tx: Color::Indexed(141),
+                title: Color::Indexed(249),
+                border: Color::Indexed(239),
+                header: Color::Indexed(221),
+                selected: Color::Indexed(197),
+                text: Color::Indexed(81),
+                dim: Color::Indexed(242),
+                axis: Color::Indexed(239),
+                tagged: Color::Indexed(141),
+                good: Color::Indexed(70),
+                warn: Color::Indexed(221),
+                error: Color::Indexed(197),
+                ..Theme::default()
+            },
+            "solarized" => Theme {
+                fg: Color::Indexed(250),
+                cpu: indexed(&[61, 33, 37, 64, 125, 160, 166, 136]),
+                memory: Color::Indexed(125),
+                swap: Color::Indexed(166),
+                rx: Color::Indexed(37),
+                tx: Color::Indexed(33),
+                title: Color::Indexed(246),
+                border: Color::Indexed(235),
+                header: Color::Indexed(136),
+                selected: Color::Indexed(166),
+                text: Color::Indexed(37),
+                dim: Color::Indexed(240),
+                axis: Color::Indexed(240),
+                tagged: Color::Indexed(125),
+                good: Color::Indexed(64),
+                warn: Color::Indexed(136),
+                error: Color::Indexed(160),
+                ..Theme::default()
+            },
+            "nord" => Theme {
+                fg: rgb(0xd8dee9),
+                cpu: vec![
+                    rgb(0x88c0d0),
+                    rgb(0x81a1c1),
+                    rgb(0xa3be8c),
+                    rgb(0xebcb8b),
+                    rgb(0xd08770),
+                    rgb(0xbf616a),
+                    rgb(0xb48ead),
+                    rgb(0x5e81ac),
+                ],
+                memory: rgb(0xbf616a),
+                swap: rgb(0xebcb8b),
+                rx: rgb(0x88c0d0),
+                tx: rgb(0x81a1c1),
+                title: rgb(0x88c0d0),
+                border: rgb(0x4c566a),
+                header: rgb(0x81a1c1),
+                selected: rgb(0xd08770),
+                text: rgb(0xd8dee9),
+                dim: rgb(0x616e88),
+                axis: rgb(0x4c566a),
+                tagged: rgb(0xb48ead),
+                good: rgb(0xa3be8c),
+                warn: rgb(0xebcb8b),
+                error: rgb(0xbf616a),
+                ..Theme::default()
+            },
+            // Dark colours only; yellow and cyan are unreadable on white.
+            "light-background" => Theme {
+                fg: Color::Black,
+                cpu: vec![
+                    Color::Blue,
+                    Color::Red,
+                    Color::Green,
+                    Color::Magenta,
+                    Color::Black,
+                    Color::Indexed(130),
+                    Color::Indexed(24),
+                ],
+                memory: Color::Red,
+                swap: Color::Indexed(130),
+                rx: Color::Blue,
+                tx: Color::Green,
+                title: Color::Blue,
+                border: Color::DarkGray,
+                header: Color::Magenta,
+                selected: Color::Red,
+                text: Color::Black,
+                dim: Color::Gray,
+                axis: Color::DarkGray,
+                tagged: Color::Indexed(90),
+                good: Color::Green,
+                warn: Color::Indexed(130),
+                error: Color::Red,
+                ..Theme::default()
+            },
+            "monochrome" => Theme {
+                fg: Color::Gray,
+                cpu: vec![Color::White, Color::Gray, Color::DarkGray],
+                memory: Color::White,
+                swap: Color::Gray,
+                rx: Color::White,
+                tx: Color::Gray,
+                title: Color::White,
+                border: Color::Gray,
+                header: Color::White,
+                selected: Color::White,
+                text: Color::Gray,
+                dim: Color::DarkGray,
+                axis: Color::DarkGray,
+                tagged: Color::White,
+                good: Color::White,
+                warn: Color::White,
+                error: Color::White,
+                ..Theme::default()
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    /// A built-in scheme, or a gotop colorscheme file given by path or by
+    /// name from rstop's or gotop's config directory.
+    pub fn load(name: &str) -> Result<Theme, String> {
+        if let Some(theme) = Theme::builtin(name) {
+            return Ok(theme);
+        }
+
+        let path = if name.ends_with(".json") || name.contains('/') {
+            Some(PathBuf::from(name))
+        } else {
+            let file = format!("{}.json", name);
+            config::config_path(&file)
+                .into_iter()
+                .chain(gotop_path(&file))
+                .find(|path| path.exists())
+        };
+        match path {
+            Some(path) => {
+                let contents = fs::read_to_string(&path)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+                from_gotop(&contents).map_err(|err| format!("{}: {}", path.display(), err))
+            }
+            None => Err(format!(
+                "unknown colorscheme {} (expected {}, a [themes] table or a gotop .json file)",
+                name,
+                BUILTIN.join(", ")
+            )),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            fg: Color::Reset,
+            bg: Color::Reset,
+            cpu: vec![
+                Color::Cyan,
+                Color::Yellow,
+                Color::Red,
+                Color::Blue,
+                Color::Green,
+                Color::Magenta,
+                Color::White,
+            ],
+            memory: Color::Red,
+            swap: Color::Yellow,
+            rx: Color::Cyan,
+            tx: Color::Blue,
+            title: Color::Cyan,
+            border: Color::Reset,
+            header: Color::Yellow,
+            selected: Color::LightRed,
+            text: Color::Cyan,
+            dim: Color::DarkGray,
+            axis: Color::Gray,
+            tagged: Color::Magenta,
+            good: Color::Green,
+            warn: Color::Yellow,
+            error: Color::Red,
+            monochrome: false,
+        }
+    }
+}
+
+/// The 16 standard colours with xterm's default RGB values, in index order.
+const BASIC: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// The channel values of the 6x6x6 cube in the 256-colour palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The RGB value of a palette or RGB colour; named ones are left alone.
+fn rgb_of(color: Color) -> Option<(u8, u8, u8)> {
+    match color {
+        Color::Rgb(r, g, b) => Some((r, g, b)),
+        Color::Indexed(i) if i < 16 => Some(BASIC[i as usize].1),
+        Color::Indexed(i) if i < 232 => {
+            let i = i - 16;
+            Some((
+                CUBE[(i / 36) as usize],
+                CUBE[(i / 6 % 6) as usize],
+                CUBE[(i % 6) as usize],
+            ))
+        }
+        Color::Indexed(i) => {
+            let level = 8 + 10 * (i - 232);
+            Some((level, level, level))
+        }
+        _ => None,
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// The basic colour with the same hue. Nearest by distance would turn most
+/// pastel colours gray.
+fn basic((r, g, b): (u8, u8, u8)) -> Color {
+    let max = r.max(g).max(b) as u16;
+    let min = r.min(g).min(b) as u16;
+    if max - min < 32 {
+        return match (max + min) / 2 {
+            0..=63 => Color::Black,
+            64..=159 => Color::DarkGray,
+            160..=223 => Color::Gray,
+            _ => Color::White,
+        };
+    }
+    // Red is 1, green 2 and blue 4 in the palette, and they mix. Channels
+    // close to the strongest one count.
+    let cut = max - (max - min) * 2 / 5;
+    let hue = (r as u16 >= cut) as usize
+        + 2 * (g as u16 >= cut) as usize
+        + 4 * (b as u16 >= cut) as usize;
+    let bright = if max >= 192 { 8 } else { 0 };
+    BASIC[hue + bright].0
+}
+
+/// The closest colour the terminal can show.
+fn degrade(color: Color, depth: ColorDepth) -> Color {
+    match (depth, color) {
+        (ColorDepth::Mono, _) => Color::Reset,
+        (ColorDepth::Indexed, Color::Rgb(r, g, b)) => {
+            let level = |c: u8| {
+                (0..CUBE.len())
+                    .min_by_key(|&i| (CUBE[i] as i32 - c as i32).abs())
+                    .unwrap_or(0) as u8
+            };
+            let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+            let average = (r as u32 + g as u32 + b as u32) / 3;
+            let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+            [cube, gray]
+                .iter()
+                .copied()
+                .min_by_key(|&i| distance((r, g, b), rgb_of(Color::Indexed(i)).unwrap_or_default()))
+                .map_or(color, Color::Indexed)
+        }
+        (ColorDepth::Basic, Color::Indexed(i)) if i < 16 => BASIC[i as usize].0,
+        (ColorDepth::Basic, _) => rgb_of(color).map_or(color, basic),
+        _ => color,
+    }
+}
+
+fn indexed(indexes: &[u8]) -> Vec<Color> {
+    indexes.iter().map(|&i| Color::Indexed(i)).collect()
+}
+
+fn rgb(rgb: u32) -> Color {
+    Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+/// `~/.config/gotop/<name>`, where gotop keeps its colorschemes.
+fn gotop_path(name: &str) -> Option<PathBuf> {
+    let rstop = config::config_path(name)?;
+    Some(rstop.parent()?.parent()?.join("gotop").join(name))
+}
+
+/// A gotop colorscheme file: 256-colour indexes with -1 for the terminal's
+/// default. Both the old `MainMem`/`SwapMem` and the newer `MemLines` keys
+/// are understood; unknown keys, such as `Name`, are ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GotopScheme {
+    fg: Option<i64>,
+    bg: Option<i64>,
+    border_label: Option<i64>,
+    border_line: Option<i64>,
+    #[serde(rename = "CPULines")]
+    cpu_lines: Option<Vec<i64>>,
+    mem_lines: Option<Vec<i64>>,
+    main_mem: Option<i64>,
+    swap_mem: Option<i64>,
+    proc_cursor: Option<i64>,
+    sparkline: Option<i64>,
+    sparklines: Option<Vec<i64>>,
+    temp_low: Option<i64>,
+    temp_high: Option<i64>,
+}
+
+/// The default theme with a gotop colorscheme's colours over it.
+fn from_gotop(contents: &str) -> Result<Theme, String> {
+    let scheme: GotopScheme = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+
+    let color = |key: &str, n: Option<i64>| -> Result<Option<Color>, String> {
+        n.map(|n| gotop_color(n).ok_or_else(|| format!("{}: bad colour {}", key, n)))
+            .transpose()
+    };
+    let colors = |key: &str, list: &Option<Vec<i64>>| -> Result<Vec<Color>, String> {
+        list.iter()
+            .flatten()
+            .map(|&n| gotop_color(n).ok_or_else(|| format!("{}: bad colour {}", key, n)))
+            .collect()
+    };
+
+    let mut theme = Theme::default();
+    let set = |slot: &mut Color, color: Option<Color>| {
+        if let Some(color) = color {
+            *slot = color;
+        }
+    };
+    set(&mut theme.fg, color("Fg", scheme.fg)?);
+    set(&mut theme.bg, color("Bg", scheme.bg)?);
+    set(&mut theme.title, color("BorderLabel", scheme.border_label)?);
+    set(&mut theme.border, color("BorderLine", scheme.border_line)?);
+    set(
+        &mut theme.selected,
+        color("ProcCursor", scheme.proc_cursor)?,
+    );
+    set(&mut theme.good, color("TempLow", scheme.temp_low)?);
+    set(&mut theme.error, color("TempHigh", scheme.temp_high)?);
+    set(&mut theme.text, color("Fg", scheme.fg)?);
+
+    let cpu = colors("CPULines", &scheme.cpu_lines)?;
+    if !cpu.is_empty() {
+        theme.cpu = cpu;
+    }
+    let mem = colors("MemLines", &scheme.mem_lines)?;
+    set(&mut theme.memory, mem.first().copied());
+    set(&mut theme.swap, mem.get(1).copied());
+    set(&mut theme.memory, color("MainMem", scheme.main_mem)?);
+    set(&mut theme.swap, color("SwapMem", scheme.swap_mem)?);
+    let sparklines = colors("Sparklines", &scheme.sparklines)?;
+    set(
+        &mut theme.rx,
+        color("Sparkline", scheme.sparkline)?.or(sparklines.first().copied()),
+    );
+    set(&mut theme.tx, sparklines.get(1).copied());
+
+    Ok(theme)
+}
+
+fn gotop_color(n: i64) -> Option<Color> {
+    match n {
+        -1 => Some(Color::Reset),
+        0..=255 => Some(Color::Indexed(n as u8)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb_to_indexed() {
+        let indexed = |r, g, b| degrade(Color::Rgb(r, g, b), ColorDepth::Indexed);
+        assert_eq!(indexed(255, 0, 0), Color::Indexed(196));
+        assert_eq!(indexed(0, 95, 135), Color::Indexed(24));
+        // Grays go to the gray ramp, which is finer than the cube's.
+        assert_eq!(indexed(128, 128, 128), Color::Indexed(244));
+        assert_eq!(
+            degrade(Color::Indexed(81), ColorDepth::Indexed),
+            Color::Indexed(81)
+        );
+    }
+
+    #[test]
+    fn rgb_to_basic() {
+        let basic = |r, g, b| degrade(Color::Rgb(r, g, b), ColorDepth::Basic);
+        assert_eq!(basic(255, 0, 0), Color::LightRed);
+        assert_eq!(basic(0, 0, 128), Color::Blue);
+        assert_eq!(basic(255, 165, 0), Color::LightYellow);
+        assert_eq!(basic(40, 40, 40), Color::Black);
+        assert_eq!(basic(200, 200, 210), Color::Gray);
+        assert_eq!(
+            degrade(Color::Indexed(196), ColorDepth::Basic),
+            Color::LightRed
+        );
+        assert_eq!(
+            degrade(Color::Indexed(9), ColorDepth::Basic),
+            Color::LightRed
+        );
+        assert_eq!(degrade(Color::Cyan, ColorDepth::Basic), Color::Cyan);
+    }
+
+    #[test]
+    fn theme_degrade() {
+        let mut theme = Theme {
+            fg: Color::Rgb(255, 0, 0),
+            cpu: vec![Color::Rgb(0, 95, 135)],
+            ..Theme::default()
+        };
+        theme.degrade(ColorDepth::TrueColor);
+        assert_eq!(theme.fg, Color::Rgb(255, 0, 0));
+
+        theme.degrade(ColorDepth::Indexed);
+        assert_eq!(theme.fg, Color::Indexed(196));
+        assert_eq!(theme.cpu, vec![Color::Indexed(24)]);
+        assert!(!theme.monochrome);
+
+        theme.degrade(ColorDepth::Mono);
+        assert_eq!(theme.fg, Color::Reset);
+        assert_eq!(theme.memory, Color::Reset);
+        assert!(theme.monochrome);
+    }
+
+    // The only test that touches the environment, so it can't race another.
+    #[test]
+    fn detect() {
+        std::env::remove_var("COLORTERM");
+        std::env::set_var("TERM", "xterm-256color");
+        std::env::set_var("NO_COLOR", "1");
+        assert_eq!(ColorDepth::detect(), ColorDepth::Mono);
+        // An empty NO_COLOR doesn't count.
+        std::env::set_var("NO_COLOR", "");
+        assert_eq!(ColorDepth::detect(), ColorDepth::Indexed);
+        std::env::remove_var("NO_COLOR");
+        assert_eq!(ColorDepth::detect(), ColorDepth::Indexed);
+
+        std::env::set_var("COLORTERM", "truecolor");
+        assert_eq!(ColorDepth::detect(), ColorDepth::TrueColor);
+        std::env::set_var("TERM", "screen-256color");
+        assert_eq!(ColorDepth::detect(), ColorDepth::Indexed);
+        std::env::remove_var("COLORTERM");
+        std::env::set_var("TERM", "xterm");
+        assert_eq!(ColorDepth::detect(), ColorDepth::Basic);
+        std::env::set_var("TERM", "dumb");
+        assert_eq!(ColorDepth::detect(), ColorDepth::Mono);
+    }
+
+    #[test]
+    fn gotop_scheme() {
+        let theme = from_gotop(
+            r#"{
+                "Name": "sample",
+                "Fg": 7,
+                "Bg": -1,
+                "BorderLabel": 4,
+                "CPULines": [1, 2, 3],
+                "MemLines": [5, 11],
+                "Sparklines": [10, 13],
+                "ProcCursor": 12
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(theme.fg, Color::Indexed(7));
+        assert_eq!(theme.text, Color::Indexed(7));
+        assert_eq!(theme.bg, Color::Reset);
+        assert_eq!(theme.title, Color::Indexed(4));
+        assert_eq!(theme.border, Theme::default().border);
+        assert_eq!(theme.cpu, indexed(&[1, 2, 3]));
+        assert_eq!(theme.memory, Color::Indexed(5));
+        assert_eq!(theme.swap, Color::Indexed(11));
+        assert_eq!(theme.rx, Color::Indexed(10));
+        assert_eq!(theme.tx, Color::Indexed(13));
+        assert_eq!(theme.selected, Color::Indexed(12));
+    }
+
+    #[test]
+    fn gotop_old_keys_win() {
+        let theme = from_gotop(
+            r#"{"MemLines": [5, 11], "MainMem": 1, "SwapMem": 2, "Sparkline": 3, "Sparklines": [10, 13]}"#,
+        )
+        .unwrap();
+        assert_eq!(theme.memory, Color::Indexed(1));
+        assert_eq!(theme.swap, Color::Indexed(2));
+        assert_eq!(theme.rx, Color::Indexed(3));
+        assert_eq!(theme.tx, Color::Indexed(13));
+    }
+
+    #[test]
+    fn gotop_errors() {
+        assert_eq!(
+            from_gotop(r#"{"Fg": 300}"#).unwrap_err(),
+            "Fg: bad colour 300"
+        );
+        assert_eq!(
+            from_gotop(r#"{"MemLines": [5, -2]}"#).unwrap_err(),
+            "MemLines: bad colour -2"
+        );
+        assert!(from_gotop("{").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::Instant;
+
+use crate::util::diskio::IoCounters;
+use crate::util::procfs::{self, Stat};
+
+/// What the process table lists: processes, or the threads of one process
+/// or of every process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadMode {
+    Off,
+    Process(sysinfo::Pid),
+    All,
+}
+
+impl ThreadMode {
+    pub fn includes(self, pid: sysinfo::Pid) -> bool {
+        match self {
+            ThreadMode::Off => false,
+            ThreadMode::Process(p) => p == pid,
+            ThreadMode::All => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Thread {
+    pub tid: sysinfo::Pid,
+    pub tgid: sysinfo::Pid,
+    pub name: String,
+    pub stat: Stat,
+    /// Percent of one CPU used since the previous sample.
+    pub cpu: f32,
+    /// Bytes read and written in total and per second, 0 when the task's
+    /// `io` file isn't readable.
+    pub read_total: u64,
+    pub write_total: u64,
+    pub read_rate: u64,
+    pub write_rate: u64,
+}
+
+/// CPU time and I/O each thread had at the previous sample. The counters
+/// in a task's `stat` and `io` are cumulative, so rates come from the
+/// difference.
+#[derive(Debug, Default)]
+pub struct ThreadTicks {
+    ticks: HashMap<sysinfo::Pid, u64>,
+    io: IoCounters,
+    at: Option<Instant>,
+}
+
+impl ThreadTicks {
+    /// Reads `/proc/<pid>/task` for every pid given. Threads seen for the
+    /// first time report 0% until the next sample.
+    pub fn sample<I>(&mut self, pids: I) -> Vec<Thread>
+    where
+        I: IntoIterator<Item = sysinfo::Pid>,
+    {
+        let now = Instant::now();
+        let elapsed = self
+            .at
+            .map(|at| now.duration_since(at).as_secs_f64())
+            .unwrap_or(0.0);
+        let hz = procfs::clock_ticks() as f64;
+        self.io.start();
+
+        let mut ticks = HashMap::new();
+        let mut threads = vec![];
+        for tgid in pids {
+            for (tid, name, stat, (read_total, write_total)) in read_tasks(tgid) {
+                let total = stat.utime + stat.stime;
+                let cpu = match self.ticks.get(&tid) {
+                    Some(prev) if elapsed > 0.0 => {
+                        total.saturating_sub(*prev) as f64 / hz / elapsed * 100.0
+                    }
+                    _ => 0.0,
+                };
+                ticks.insert(tid, total);
+                let (read_rate, write_rate) = self.io.rates(tid, read_total, write_total);
+                threads.push(Thread {
+                    tid,
+                    tgid,
+                    name,
+                    stat,
+                    cpu: cpu as f32,
+                    read_total,
+                    write_total,
+                    read_rate,
+                    write_rate,
+                });
+            }
+        }
+
+        self.ticks = ticks;
+        self.at = Some(now);
+        threads
+    }
+}
+
+fn read_tasks(tgid: sysinfo::Pid) -> Vec<(sysinfo::Pid, String, Stat, (u64, u64))> {
+    let dir = match fs::read_dir(format!("/proc/{}/task", tgid)) {
+        Ok(dir) => dir,
+        Err(_) => return vec![],
+    };
+
+    dir.filter_map(|entry| {
+        let tid: sysinfo::Pid = entry.ok()?.file_name().to_str()?.parse().ok()?;
+        let base = format!("/proc/{}/task/{}", tgid, tid);
+        let stat = procfs::read_stat_file(&format!("{}/stat", base))?;
+        let name = fs::read_to_string(format!("{}/comm", base))
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default();
+        let io = procfs::read_io_file(&format!("{}/io", base)).unwrap_or((0, 0));
+        Some((tid, name, stat, io))
+    })
+    .collect()
+}
@@ -0,0 +1,124 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::util::app::{ProcessInfo, ProcessRow};
+
+/// Lays `process_list` out as a forest keyed on parent PIDs. The list is
+/// expected to be sorted already, so children keep that order within each
+/// sibling group. Collapsed nodes hide their descendants and report the
+/// summed CPU and memory of the whole subtree instead.
+pub fn build_tree_rows(
+    process_list: &[ProcessInfo],
+    collapsed: &HashSet<sysinfo::Pid>,
+) -> Vec<ProcessRow> {
+    let present: HashSet<sysinfo::Pid> = process_list.iter().map(|p| p.pid).collect();
+    let mut children: HashMap<sysinfo::Pid, Vec<&ProcessInfo>> = HashMap::new();
+    let mut roots = vec![];
+
+    for process in process_list {
+        match process.parent {
+            Some(parent) if parent != process.pid && present.contains(&parent) => {
+                children.entry(parent).or_default().push(process)
+            }
+            _ => roots.push(process),
+        }
+    }
+
+    let mut builder = TreeBuilder {
+        children: &children,
+        collapsed,
+        visited: HashSet::new(),
+        rows: vec![],
+    };
+    let root_count = roots.len();
+    for (i, root) in roots.into_iter().enumerate() {
+        builder.push_subtree(root, "", i + 1 == root_count, true);
+    }
+
+    builder.rows
+}
+
+struct TreeBuilder<'a> {
+    children: &'a HashMap<sysinfo::Pid, Vec<&'a ProcessInfo>>,
+    collapsed: &'a HashSet<sysinfo::Pid>,
+    visited: HashSet<sysinfo::Pid>,
+    rows: Vec<ProcessRow>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn push_subtree(&mut self, process: &ProcessInfo, guide: &str, last: bool, root: bool) {
+        if !self.visited.insert(process.pid) {
+            return;
+        }
+
+        let children = self.children;
+        let kids = children
+            .get(&process.pid)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[]);
+        let is_collapsed = !kids.is_empty() && self.collapsed.contains(&process.pid);
+
+        let branch = if root {
+            ""
+        } else if last {
+            "└─"
+        } else {
+            "├─"
+        };
+        let marker = if is_collapsed { "+ " } else { " " };
+        let prefix = if root && !is_collapsed {
+            String::new()
+        } else {
+            format!("{}{}{}", guide, branch, marker)
+        };
+
+        let mut info = process.clone();
+        if is_collapsed {
+            let (cpu, mem) = subtree_usage(process, children, &mut HashSet::new());
+            info.cpu = cpu;
+            info.mem = mem;
+        }
+
+        self.rows.push(ProcessRow {
+            prefix,
+            collapsed: is_collapsed,
+            has_children: !kids.is_empty(),
+            ..ProcessRow::plain(info)
+        });
+
+        if is_collapsed {
+            return;
+        }
+
+        let child_guide = if root {
+            String::new()
+        } else if last {
+            format!("{}  ", guide)
+        } else {
+            format!("{}│ ", guide)
+        };
+        for (i, child) in kids.iter().enumerate() {
+            self.push_subtree(child, &child_guide, i + 1 == kids.len(), false);
+        }
+    }
+}
+
+fn subtree_usage(
+    process: &ProcessInfo,
+    children: &HashMap<sysinfo::Pid, Vec<&ProcessInfo>>,
+    visited: &mut HashSet<sysinfo::Pid>,
+) -> (f32, u64) {
+    if !visited.insert(process.pid) {
+        return (0.0, 0);
+    }
+    let (mut cpu, mut mem) = (process.cpu, process.mem);
+
+    if let Some(kids) = children.get(&process.pid) {
+        for child in kids {
+            let (c, m) = subtree_usage(child, children, visited);
+            cpu += c;
+            mem += m;
+        }
+    }
+
+    (cpu, mem)
+}
@@ -1,55 +1,950 @@
 use crate::logger::Logger;
-use crate::util::{app::SortBy, App};
+use crate::util::{
+    app::{GroupBy, InputMode, SortOrder},
+    columns::{self, Column},
+    deleted::Marker,
+    detail::{self, Detail, DetailTab},
+    files::FdKind,
+    keymap::HelpLine,
+    procfs,
+    search::Search,
+    security,
+    settings::Widget,
+    signal,
+    theme::Theme,
+    threads::ThreadMode,
+    units, App,
+};
 
+use std::time::{SystemTime, UNIX_EPOCH};
 use tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     symbols,
-    text::Span,
-    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Row, Sparkline, Table, TableState},
+    text::{Span, Spans},
+    widgets::{
+        Axis, Block, Borders, Cell, Chart, Clear, Dataset, Gauge, GraphType, List, ListItem,
+        ListState, Paragraph, Row, Sparkline, Table, TableState, Tabs, Wrap,
+    },
     Frame,
 };
 
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App, logger: &mut Logger) {
+    f.render_widget(
+        Block::default().style(Style::default().fg(app.theme.fg).bg(app.theme.bg)),
+        f.size(),
+    );
+
+    let shows = |w| app.settings.shows(w);
+    let rows = split_shown(
+        Rect {
+            y: f.size().y + 2,
+            height: f.size().height.saturating_sub(4),
+            ..f.size()
+        },
+        Direction::Vertical,
+        &[
+            shows(Widget::Cpu),
+            shows(Widget::Disk)
+                || shows(Widget::Temp)
+                || shows(Widget::Battery)
+                || shows(Widget::Mem),
+            shows(Widget::Net) || shows(Widget::Proc),
+        ],
+    );
+
+    if let Some(area) = rows[0] {
+        draw_first_row(f, app, area, logger);
+    }
+    if let Some(area) = rows[1] {
+        draw_second_row(f, app, area, logger);
+    }
+    if let Some(area) = rows[2] {
+        draw_third_row(f, app, area);
+    }
+    if app.settings.statusbar {
+        draw_statusbar(f, app);
+    }
+    draw_status_line(f, app);
+
+    match app.input_mode {
+        InputMode::SignalPicker => draw_signal_picker(f, app),
+        InputMode::ConfirmSignal => draw_signal_confirm(f, app),
+        InputMode::Affinity => draw_affinity_editor(f, app),
+        InputMode::Columns => draw_column_picker(f, app),
+        InputMode::Detail => draw_detail(f, app),
+        InputMode::Files => draw_open_files(f, app),
+        InputMode::Events => draw_event_log(f, app),
+        InputMode::Deleted => draw_stale_list(f, app),
+        InputMode::Help => draw_help(f, app),
+        _ => {}
+    }
+}
+
+/// A bordered block in the theme's border colour.
+fn bordered<'a>(theme: &Theme) -> Block<'a> {
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border))
+}
+
+/// Splits `area` evenly between the parts that are shown; hidden parts get
+/// `None` and leave their space to the others.
+fn split_shown(area: Rect, direction: Direction, shown: &[bool]) -> Vec<Option<Rect>> {
+    let count = shown.iter().filter(|&&s| s).count() as u32;
+    let mut chunks = Layout::default()
+        .constraints(vec![Constraint::Ratio(1, count.max(1)); count as usize])
+        .direction(direction)
+        .split(area)
+        .into_iter();
+    shown
+        .iter()
+        .map(|&s| if s { chunks.next() } else { None })
+        .collect()
+}
+
+/// A `width` x `height` rectangle in the middle of `area`, shrunk to fit.
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn signal_targets_label(app: &App) -> String {
+    match app.signal_targets.as_slice() {
+        [(pid, name)] => format!("{} ({})", pid, name),
+        targets => format!("{} processes", targets.len()),
+    }
+}
+
+fn draw_signal_picker<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let area = centered_rect(36, signal::SIGNALS.len() as u16 + 2, f.size());
+
+    let items: Vec<ListItem> = signal::SIGNALS
+        .iter()
+        .map(|(name, number)| ListItem::new(format!("{:>2} {}", number, name)))
+        .collect();
+    let list = List::new(items)
+        .block(
+            bordered(&app.theme).title(Span::styled(
+                format!(" Signal {} ", signal_targets_label(app)),
+                Style::default()
+                    .fg(app.theme.title)
+                    .add_modifier(Modifier::BOLD),
+            )),
+        )
+        .highlight_style(app.theme.selected_style())
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    state.select(Some(app.signal_index));
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// A boxed list of `[x] label` lines with a cursor, used by the affinity
+/// editor and the column picker.
+fn draw_checklist<B: Backend>(
+    f: &mut Frame<B>,
+    theme: &Theme,
+    title: String,
+    hint: &str,
+    items: Vec<(bool, String)>,
+    cursor: usize,
+) {
+    let width = items
+        .iter()
+        .map(|(_, label)| label.len() + 8)
+        .chain(vec![title.len() + 4, hint.len() + 4])
+        .max()
+        .unwrap_or(20) as u16;
+    let area = centered_rect(width, items.len() as u16 + 3, f.size());
+
+    let items: Vec<ListItem> = items
+        .into_iter()
+        .map(|(checked, label)| {
+            ListItem::new(format!("[{}] {}", if checked { "x" } else { " " }, label))
+        })
+        .chain(vec![ListItem::new(Span::styled(
+            hint.to_string(),
+            theme.dim_style(),
+        ))])
+        .collect();
+    let list = List::new(items)
+        .block(
+            bordered(theme).title(Span::styled(
+                title,
+                Style::default()
+                    .fg(theme.title)
+                    .add_modifier(Modifier::BOLD),
+            )),
+        )
+        .highlight_style(theme.selected_style())
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_affinity_editor<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let title = match app.affinity_targets.as_slice() {
+        [(pid, name)] => format!(" Affinity {} ({}) ", pid, name),
+        targets => format!(" Affinity {} processes ", targets.len()),
+    };
+    let items = app
+        .affinity
+        .iter()
+        .enumerate()
+        .map(|(cpu, allowed)| (*allowed, format!("CPU {}", cpu)))
+        .collect();
+
+    draw_checklist(
+        f,
+        &app.theme,
+        title,
+        "space toggle, a all, enter apply",
+        items,
+        app.affinity_cursor,
+    );
+}
+
+fn draw_column_picker<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let items = columns::ALL_COLUMNS
+        .iter()
+        .map(|column| {
+            (
+                app.process.columns.contains(column),
+                column.title().to_string(),
+            )
+        })
+        .collect();
+
+    draw_checklist(
+        f,
+        &app.theme,
+        String::from(" Columns "),
+        "space toggle, esc close",
+        items,
+        app.column_cursor,
+    );
+}
+
+fn draw_detail<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let detail = match &app.detail {
+        Some(detail) => detail,
+        None => return,
+    };
+    let theme = &app.theme;
+
+    let size = f.size();
+    let area = Rect::new(size.x, size.y, size.width, size.height.saturating_sub(1));
+    let chunks = Layout::default()
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .direction(Direction::Vertical)
+        .split(area);
+
+    let titles = DetailTab::ALL
+        .iter()
+        .map(|tab| Spans::from(tab.title()))
+        .collect();
+    let tabs = Tabs::new(titles)
+        .select(
+            DetailTab::ALL
+                .iter()
+                .position(|tab| *tab == detail.tab)
+                .unwrap_or(0),
+        )
+        .style(Style::default().fg(theme.axis))
+        .highlight_style(
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    f.render_widget(Clear, area);
+    f.render_widget(tabs, chunks[0]);
+
+    let scroll_title = |name: &str| {
+        format!(
+            " {} ({}) {} {}/{} ",
+            detail.pid,
+            detail.name,
+            name,
+            (detail.scroll + 1).min(detail.rows()),
+            detail.rows()
+        )
+    };
+    match detail.tab {
+        DetailTab::Overview => draw_detail_overview(f, theme, detail, chunks[1]),
+        DetailTab::Environment => {
+            let rows = detail
+                .environ
+                .iter()
+                .map(|(name, value)| {
+                    let value = if detail.show_secrets {
+                        value.clone()
+                    } else {
+                        security::mask_env_value(name, value)
+                    };
+                    vec![name.clone(), value]
+                })
+                .collect();
+            let mut title = scroll_title("environment");
+            if !detail.show_secrets {
+                title += "[secrets masked, s shows] ";
+            }
+            if let Some(err) = &detail.environ_error {
+                let text =
+                    Paragraph::new(format!("Can't read /proc/{}/environ: {}", detail.pid, err))
+                        .style(theme.error_style())
+                        .block(bordered(theme).title(title));
+                f.render_widget(text, chunks[1]);
+                return;
+            }
+            draw_detail_list(
+                f,
+                theme,
+                chunks[1],
+                title,
+                vec!["Name", "Value"],
+                &[Constraint::Percentage(30), Constraint::Percentage(70)],
+                rows,
+                detail.scroll,
+            );
+        }
+        DetailTab::Limits => {
+            let value = |v: Option<u64>| match v {
+                Some(v) => v.to_string(),
+                None => String::from("unlimited"),
+            };
+            let rows = detail
+                .limits
+                .iter()
+                .map(|(name, limit)| {
+                    vec![
+                        name.clone(),
+                        value(limit.soft),
+                        value(limit.hard),
+                        limit.units.clone(),
+                    ]
+                })
+                .collect();
+            draw_detail_list(
+                f,
+                theme,
+                chunks[1],
+                scroll_title("limits"),
+                vec!["Limit", "Soft", "Hard", "Units"],
+                &[
+                    Constraint::Length(26),
+                    Constraint::Length(21),
+                    Constraint::Length(21),
+                    Constraint::Min(0),
+                ],
+                rows,
+                detail.scroll,
+            );
+        }
+        DetailTab::Security => {
+            let rows = detail
+                .security
+                .iter()
+                .map(|(label, value)| vec![label.clone(), value.clone()])
+                .collect();
+            draw_detail_list(
+                f,
+                theme,
+                chunks[1],
+                scroll_title("security"),
+                vec!["Item", "Value"],
+                &[Constraint::Length(26), Constraint::Min(0)],
+                rows,
+                detail.scroll,
+            );
+        }
+    }
+}
+
+/// A scrollable table for the list tabs of the detail pane.
+fn draw_detail_list<B: Backend>(
+    f: &mut Frame<B>,
+    theme: &Theme,
+    area: Rect,
+    title: String,
+    header: Vec<&str>,
+    widths: &[Constraint],
+    rows: Vec<Vec<String>>,
+    scroll: usize,
+) {
+    let block = bordered(theme).title(title);
+    let rows = rows.into_iter().skip(scroll).map(Row::new);
+    let table = Table::new(rows)
+        .header(Row::new(header).style(theme.header_style()))
+        .block(block)
+        .widths(widths);
+    f.render_widget(table, area);
+}
+
+fn draw_detail_overview<B: Backend>(f: &mut Frame<B>, theme: &Theme, detail: &Detail, area: Rect) {
     let chunks = Layout::default()
+        .constraints([
+            Constraint::Length(9),
+            Constraint::Min(8),
+            Constraint::Length(12),
+        ])
+        .direction(Direction::Vertical)
+        .split(area);
+
+    let label = |s: &'static str| Span::styled(format!("{:<9}", s), theme.header_style());
+    let parents: Vec<String> = detail
+        .parents
+        .iter()
+        .map(|(pid, name)| format!("{} {}", pid, name))
+        .collect();
+    let started = match detail.started {
+        Some(started) => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(started);
+            format!(
+                "{} (up {})",
+                units::format_timestamp(started),
+                units::format_duration(now.saturating_sub(started))
+            )
+        }
+        None => String::from("?"),
+    };
+
+    let text = vec![
+        Spans::from(vec![
+            label("State"),
+            Span::raw(format!(
+                "{}  threads {}  nice {}",
+                detail.state, detail.threads, detail.nice
+            )),
+        ]),
+        Spans::from(vec![label("Command"), Span::raw(detail.cmd.clone())]),
+        Spans::from(vec![label("Exe"), Span::raw(detail.exe.clone())]),
+        Spans::from(vec![label("Cwd"), Span::raw(detail.cwd.clone())]),
+        Spans::from(vec![
+            label("User"),
+            Span::raw(format!("{}  group {}", detail.uids, detail.gids)),
+        ]),
+        Spans::from(vec![label("Parents"), Span::raw(parents.join(" → "))]),
+        Spans::from(vec![label("Started"), Span::raw(started)]),
+    ];
+
+    let mut title = format!(" {} ({}) ", detail.pid, detail.name);
+    if detail.exited {
+        title += "[exited] ";
+    }
+    let info = Paragraph::new(text).block(
+        bordered(theme).title(Span::styled(
+            title,
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        )),
+    );
+
+    f.render_widget(info, chunks[0]);
+
+    let charts = Layout::default()
         .constraints([
             Constraint::Ratio(1, 3),
             Constraint::Ratio(1, 3),
             Constraint::Ratio(1, 3),
         ])
-        .vertical_margin(2)
-        .split(f.size());
-
-    draw_first_row(f, app, chunks[0], logger);
-    draw_second_row(f, app, chunks[1], logger);
-    draw_third_row(f, app, chunks[2]);
-}
-
-fn map_color_to_index(i: usize) -> Color {
-    match i {
-        0 => Color::Cyan,
-        1 => Color::Yellow,
-        2 => Color::Red,
-        3 => Color::Blue,
-        4 => Color::Green,
-        5 => Color::Magenta,
-        _ => Color::White,
+        .direction(Direction::Horizontal)
+        .split(chunks[1]);
+
+    draw_history(
+        f,
+        theme,
+        &detail.cpu_history,
+        " CPU % ",
+        theme.cpu(0),
+        charts[0],
+        |v| format!("{:.1}", v),
+    );
+    draw_history(
+        f,
+        theme,
+        &detail.mem_history,
+        " Memory ",
+        theme.memory,
+        charts[1],
+        |v| units::format_bytes(v as u64),
+    );
+    draw_history(
+        f,
+        theme,
+        &detail.io_history,
+        " Disk I/O /s ",
+        theme.swap,
+        charts[2],
+        |v| units::format_bytes(v as u64),
+    );
+
+    draw_memory_map(f, theme, detail, chunks[2]);
+}
+
+/// The smaps_rollup breakdown next to the largest mappings.
+fn draw_memory_map<B: Backend>(f: &mut Frame<B>, theme: &Theme, detail: &Detail, area: Rect) {
+    let chunks = Layout::default()
+        .constraints([Constraint::Length(30), Constraint::Min(0)])
+        .direction(Direction::Horizontal)
+        .split(area);
+
+    let text = match &detail.memory {
+        Some(memory) => {
+            let line = |name: &'static str, bytes: u64| {
+                Spans::from(vec![
+                    Span::styled(format!("{:<10}", name), theme.header_style()),
+                    Span::raw(format!("{:>10}", units::format_bytes(bytes))),
+                ])
+            };
+            vec![
+                line("RSS", memory.rss),
+                line("PSS", memory.pss),
+                line("USS", memory.uss()),
+                line("Shared", memory.shared()),
+                line("  clean", memory.shared_clean),
+                line("  dirty", memory.shared_dirty),
+                line("Private", memory.uss()),
+                line("  clean", memory.private_clean),
+                line("  dirty", memory.private_dirty),
+                line("Swap", memory.swap),
+            ]
+        }
+        None => vec![Spans::from(Span::styled(
+            "smaps_rollup not readable",
+            theme.dim_style(),
+        ))],
+    };
+    let breakdown = Paragraph::new(text).block(bordered(theme).title(" Memory "));
+    f.render_widget(breakdown, chunks[0]);
+
+    let rows = detail.mappings.iter().skip(detail.scroll).map(|m| {
+        let path = if m.path.is_empty() {
+            String::from("[anon]")
+        } else {
+            m.path.clone()
+        };
+        Row::new(vec![
+            Cell::from(units::format_bytes(m.size())),
+            Cell::from(m.perms.clone()),
+            Cell::from(path),
+        ])
+    });
+    let title = format!(
+        " Mappings, largest first ({}/{}) ",
+        (detail.scroll + 1).min(detail.mappings.len()),
+        detail.mappings.len()
+    );
+    let table = Table::new(rows)
+        .header(Row::new(vec!["Size", "Perms", "Path"]).style(theme.header_style()))
+        .block(bordered(theme).title(title))
+        .widths(&[
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Min(0),
+        ]);
+    f.render_widget(table, chunks[1]);
+}
+
+fn draw_history<B, F>(
+    f: &mut Frame<B>,
+    theme: &Theme,
+    history: &[f64],
+    title: &str,
+    color: Color,
+    area: Rect,
+    format: F,
+) where
+    B: Backend,
+    F: Fn(f64) -> String,
+{
+    let points: Vec<(f64, f64)> = history
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i as f64, *v))
+        .collect();
+    let max = history.iter().cloned().fold(0.0, f64::max).max(1.0);
+    let last = history.last().cloned().unwrap_or(0.0);
+
+    let datasets = vec![Dataset::default()
+        .marker(symbols::Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(color))
+        .data(&points)];
+
+    let chart = Chart::new(datasets)
+        .block(bordered(theme).title(format!("{}{} ", title, format(last))))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(theme.axis))
+                .bounds([0.0, detail::HISTORY_LEN as f64]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(theme.axis))
+                .labels(vec![Span::raw("0"), Span::raw(format(max))])
+                .bounds([0.0, max]),
+        );
+    f.render_widget(chart, area);
+}
+
+fn draw_open_files<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let open_files = match &app.open_files {
+        Some(open_files) => open_files,
+        None => return,
+    };
+    let theme = &app.theme;
+
+    let size = f.size();
+    let area = Rect::new(size.x, size.y, size.width, size.height.saturating_sub(1));
+    let title = Span::styled(
+        format!(
+            " {} ({}): {} ",
+            open_files.pid,
+            open_files.name,
+            open_files.describe()
+        ),
+        Style::default()
+            .fg(theme.title)
+            .add_modifier(Modifier::BOLD),
+    );
+    let block = bordered(theme).title(title);
+
+    f.render_widget(Clear, area);
+    if let Some(err) = &open_files.error {
+        let text = Paragraph::new(format!("Can't read /proc/{}/fd: {}", open_files.pid, err))
+            .style(theme.error_style())
+            .block(block);
+        f.render_widget(text, area);
+        return;
     }
+
+    let rows = open_files.entries.iter().map(|entry| {
+        let style = match entry.kind {
+            FdKind::File => Style::default().fg(theme.fg),
+            FdKind::Socket => Style::default().fg(theme.rx),
+            FdKind::Pipe => theme.warn_style(),
+            FdKind::AnonInode | FdKind::Other => theme.dim_style(),
+        };
+        Row::new(vec![
+            Cell::from(entry.fd.to_string()),
+            Cell::from(entry.kind.label()),
+            Cell::from(entry.target.clone()),
+        ])
+        .style(style)
+    });
+
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["FD", "Type", "Target"])
+                .style(theme.header_style())
+                .bottom_margin(1),
+        )
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Min(0),
+        ]);
+
+    let mut state = TableState::default();
+    if !open_files.entries.is_empty() {
+        state.select(Some(open_files.cursor));
+    }
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+/// Processes still running deleted binaries or libraries.
+fn draw_stale_list<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let stale = match &app.stale {
+        Some(stale) => stale,
+        None => return,
+    };
+    let theme = &app.theme;
+    let size = f.size();
+    let area = Rect::new(size.x, size.y, size.width, size.height.saturating_sub(1));
+
+    let rows = stale.entries.iter().map(|entry| {
+        let mut files = vec![];
+        if let Some(exe) = &entry.exe {
+            files.push(format!("{} (exe)", exe));
+        }
+        files.extend(entry.libs.iter().cloned());
+        let style = match entry.marker() {
+            Marker::Exe => theme.error_style(),
+            Marker::Lib => theme.warn_style(),
+        };
+        Row::new(vec![
+            Cell::from(entry.pid.to_string()),
+            Cell::from(entry.name.clone()),
+            Cell::from(entry.user.clone()),
+            Cell::from(files.join(", ")),
+        ])
+        .style(style)
+    });
+
+    let title = Span::styled(
+        format!(
+            " {} processes use deleted files (r rescans) ",
+            stale.entries.len()
+        ),
+        Style::default()
+            .fg(theme.title)
+            .add_modifier(Modifier::BOLD),
+    );
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["Pid", "Process", "User", "Deleted files"])
+                .style(theme.header_style())
+                .bottom_margin(1),
+        )
+        .block(bordered(theme).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Length(8),
+            Constraint::Length(16),
+            Constraint::Length(10),
+            Constraint::Min(0),
+        ]);
+
+    let mut state = TableState::default();
+    if !stale.entries.is_empty() {
+        state.select(Some(stale.cursor));
+    }
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+/// Spawn and exit events, newest first.
+fn draw_event_log<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let lifecycle = &app.process.lifecycle;
+    let theme = &app.theme;
+    let size = f.size();
+    let area = Rect::new(size.x, size.y, size.width, size.height.saturating_sub(1));
+
+    let rows = lifecycle.log.iter().rev().map(|event| {
+        let (what, style) = if event.spawned {
+            ("spawn", theme.good_style())
+        } else {
+            ("exit", theme.error_style())
+        };
+        let cmd = if event.cmd.is_empty() {
+            event.name.clone()
+        } else {
+            event.cmd.clone()
+        };
+        Row::new(vec![
+            Cell::from(units::format_timestamp(event.at)),
+            Cell::from(what).style(style),
+            Cell::from(event.pid.to_string()),
+            Cell::from(event.name.clone()),
+            Cell::from(cmd),
+        ])
+    });
+
+    let title = Span::styled(
+        format!(" Process events ({}) ", lifecycle.log.len()),
+        Style::default()
+            .fg(theme.title)
+            .add_modifier(Modifier::BOLD),
+    );
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["Time", "Event", "Pid", "Name", "Command"])
+                .style(theme.header_style())
+                .bottom_margin(1),
+        )
+        .block(bordered(theme).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Length(19),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Length(16),
+            Constraint::Min(0),
+        ]);
+
+    let mut state = TableState::default();
+    if !lifecycle.log.is_empty() {
+        state.select(Some(lifecycle.cursor));
+    }
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+/// Every action with its current keys, grouped by the overlay it works in.
+fn draw_help<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let theme = &app.theme;
+    let size = f.size();
+    let area = Rect::new(size.x, size.y, size.width, size.height.saturating_sub(1));
+
+    let rows = app.keymap.help().into_iter().map(|line| match line {
+        HelpLine::Section(context) => Row::new(vec![Cell::from(Span::styled(
+            context.title(),
+            Style::default()
+                .fg(theme.header)
+                .add_modifier(Modifier::BOLD),
+        ))]),
+        HelpLine::Entry { keys, action } => {
+            let keys = if keys.is_empty() {
+                Cell::from("(unbound)").style(theme.dim_style())
+            } else {
+                Cell::from(keys)
+            };
+            Row::new(vec![
+                keys,
+                Cell::from(action.description()),
+                Cell::from(action.name()).style(theme.dim_style()),
+            ])
+        }
+    });
+
+    let title = Span::styled(
+        " Keys (a count before a key repeats it, e.g. 5j) ",
+        Style::default()
+            .fg(theme.title)
+            .add_modifier(Modifier::BOLD),
+    );
+    let table = Table::new(rows)
+        .block(bordered(theme).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Length(24),
+            Constraint::Length(34),
+            Constraint::Min(0),
+        ]);
+
+    let mut state = TableState::default();
+    state.select(Some(app.help_cursor));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+fn draw_signal_confirm<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let area = centered_rect(50, 5, f.size());
+    let (name, _) = signal::SIGNALS[app.signal_index];
+
+    let text = Spans::from(vec![
+        Span::raw("Send "),
+        Span::styled(name, Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(format!(" to {}? ", signal_targets_label(app))),
+        Span::styled("[y/N]", app.theme.header_style()),
+    ]);
+    let paragraph = Paragraph::new(text)
+        .block(bordered(&app.theme).title(" Confirm "))
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// gotop's statusbar: hostname, clock and uptime on the top line.
+fn draw_statusbar<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let size = f.size();
+    if size.height == 0 {
+        return;
+    }
+    let area = Rect::new(size.x, size.y, size.width, 1);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let uptime = procfs::uptime()
+        .map(|secs| format!("up {}", units::format_duration(secs)))
+        .unwrap_or_default();
+    let text = format!(
+        " {}  {}  {}",
+        procfs::hostname().unwrap_or_default(),
+        units::format_timestamp(now),
+        uptime
+    );
+    f.render_widget(
+        Paragraph::new(Span::styled(text, Style::default().fg(app.theme.title))),
+        area,
+    );
+}
+
+/// Uses the bottom margin row for prompts and messages.
+fn draw_status_line<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let size = f.size();
+    if size.height == 0 {
+        return;
+    }
+    let area = Rect::new(size.x, size.y + size.height - 1, size.width, 1);
+
+    let mut spans = vec![];
+    if let Some(label) = app.input_mode.prompt_label() {
+        spans.push(Span::styled(
+            label,
+            Style::default()
+                .fg(app.theme.header)
+                .add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(format!("{}_", app.prompt)));
+    } else if let Some(query) = &app.process.query {
+        spans.push(Span::styled("filter: ", app.theme.header_style()));
+        spans.push(Span::raw(query.text.clone()));
+    }
+
+    if let Some(status) = &app.status {
+        if !spans.is_empty() {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(Span::styled(status.clone(), app.theme.error_style()));
+    } else if app.input_mode == InputMode::Filter && !app.process.saved_filters.is_empty() {
+        let names: Vec<String> = app
+            .process
+            .saved_filters
+            .keys()
+            .map(|name| format!("@{}", name))
+            .collect();
+        spans.push(Span::styled(
+            format!("  saved: {}", names.join(" ")),
+            app.theme.dim_style(),
+        ));
+    }
+
+    f.render_widget(Paragraph::new(Spans::from(spans)), area);
 }
 
 fn draw_first_row<B>(f: &mut Frame<B>, app: &mut App, area: Rect, _logger: &mut Logger)
 where
     B: Backend,
 {
+    let theme = &app.theme;
     let mut datasets = vec![];
     for (i, ele) in app.cpu_usage_points.iter().enumerate() {
+        let name = if app.settings.average_cpu {
+            String::from(" Avg ")
+        } else {
+            format!(" CPU{} ", i)
+        };
         datasets.push(
             Dataset::default()
-                .name(format!(" CPU{} ", i))
+                .name(name)
                 .marker(symbols::Marker::Braille)
                 .graph_type(GraphType::Line)
-                .style(Style::default().fg(map_color_to_index(i)))
+                .style(Style::default().fg(theme.cpu(i)))
                 .data(&ele),
         );
     }
@@ -58,27 +953,25 @@ where
 
     let chart = Chart::new(datasets)
         .block(
-            Block::default()
-                .title(Span::styled(
-                    " CPU Usage ",
-                    Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD),
-                ))
-                .borders(Borders::ALL),
+            bordered(theme).title(Span::styled(
+                " CPU Usage ",
+                Style::default()
+                    .fg(theme.title)
+                    .add_modifier(Modifier::BOLD),
+            )),
         )
         //.hidden_legend_constraints(chart_legend_constraints)
         .x_axis(
             Axis::default()
                 .title("X Axis")
-                .style(Style::default().fg(Color::Gray))
+                .style(Style::default().fg(theme.axis))
                 .labels(vec![])
-                .bounds([0.0, 20.0]),
+                .bounds([0.0, app.max_capacity_queue as f64]),
         )
         .y_axis(
             Axis::default()
                 .title("Y Axis")
-                .style(Style::default().fg(Color::Gray))
+                .style(Style::default().fg(theme.axis))
                 .labels(vec![])
                 .bounds([-20.0, 20.0]),
         );
@@ -89,86 +982,131 @@ fn draw_second_row<B>(f: &mut Frame<B>, app: &mut App, area: Rect, logger: &mut
 where
     B: Backend,
 {
-    let chunks_horiz = Layout::default()
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .direction(Direction::Horizontal)
-        .split(area);
+    let shows = |w| app.settings.shows(w);
+    let chunks_horiz = split_shown(
+        area,
+        Direction::Horizontal,
+        &[
+            shows(Widget::Disk) || shows(Widget::Temp) || shows(Widget::Battery),
+            shows(Widget::Mem),
+        ],
+    );
+    let chunks = match chunks_horiz[0] {
+        Some(left) => split_shown(
+            left,
+            Direction::Vertical,
+            &[
+                shows(Widget::Disk),
+                shows(Widget::Temp),
+                shows(Widget::Battery),
+            ],
+        ),
+        None => vec![None, None, None],
+    };
 
-    let chunks = Layout::default()
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .direction(Direction::Vertical)
-        .split(chunks_horiz[0]);
+    if let Some(area) = chunks[0] {
+        draw_disk_table(f, app, area);
+    }
+    if let Some(area) = chunks[1] {
+        draw_temperatures(f, app, area);
+    }
+    if let Some(area) = chunks[2] {
+        draw_batteries(f, app, area);
+    }
+    if let Some(area) = chunks_horiz[1] {
+        draw_disk_usage(f, app, area, logger);
+    }
+}
 
+fn draw_disk_table<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
     let rows = app.disk_usage.iter().map(|x| Row::new(x.clone()));
     let table = Table::new(rows)
         .header(Row::new(vec!["Name", "Mount", "Free"]))
-        .block(
-            Block::default()
-                .title(" Memory Usage ")
-                .borders(Borders::ALL),
-        )
+        .block(bordered(&app.theme).title(" Memory Usage "))
         .widths(&[
             Constraint::Ratio(1, 3),
             Constraint::Ratio(1, 3),
             Constraint::Ratio(1, 3),
         ]);
-    f.render_widget(table, chunks[0]);
+    f.render_widget(table, area);
+}
 
+fn draw_temperatures<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
     let rows = app.temps.iter().map(|x| Row::new(x.clone()));
     let table = Table::new(rows)
-        .block(
-            Block::default()
-                .title(" Temperatures ")
-                .borders(Borders::ALL),
-        )
+        .block(bordered(&app.theme).title(" Temperatures "))
         .widths(&[Constraint::Percentage(50), Constraint::Percentage(50)]);
-    f.render_widget(table, chunks[1]);
+    f.render_widget(table, area);
+}
 
-    draw_disk_usage(f, app, chunks_horiz[1], logger);
+fn draw_batteries<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let block = bordered(&app.theme).title(" Battery ");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if app.batteries.is_empty() {
+        f.render_widget(
+            Paragraph::new("no battery found").style(app.theme.dim_style()),
+            inner,
+        );
+        return;
+    }
+    for (i, battery) in app.batteries.iter().enumerate().take(inner.height as usize) {
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(app.theme.title))
+            .percent(battery.percent)
+            .label(format!(
+                "{} {}% {}",
+                battery.name, battery.percent, battery.status
+            ));
+        f.render_widget(
+            gauge,
+            Rect::new(inner.x, inner.y + i as u16, inner.width, 1),
+        );
+    }
 }
 
 fn draw_disk_usage<B>(f: &mut Frame<B>, app: &mut App, area: Rect, logger: &mut Logger)
 where
     B: Backend,
 {
+    let theme = &app.theme;
     let datasets = vec![
         Dataset::default()
             .name(format!(" Memory "))
             .marker(symbols::Marker::Braille)
             .graph_type(GraphType::Line)
-            .style(Style::default().fg(Color::Red))
+            .style(Style::default().fg(theme.memory))
             .data(app.memory.memory_queue.vec()),
         Dataset::default()
             .name(format!(" Swap "))
             .marker(symbols::Marker::Braille)
             .graph_type(GraphType::Line)
-            .style(Style::default().fg(Color::Yellow))
+            .style(Style::default().fg(theme.swap))
             .data(app.memory.swap_queue.vec()),
     ];
 
     let chart = Chart::new(datasets)
         .block(
-            Block::default()
-                .title(Span::styled(
-                    " Disk Usage ",
-                    Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD),
-                ))
-                .borders(Borders::ALL),
+            bordered(theme).title(Span::styled(
+                " Disk Usage ",
+                Style::default()
+                    .fg(theme.title)
+                    .add_modifier(Modifier::BOLD),
+            )),
         )
         //.hidden_legend_constraints(chart_legend_constraints)
         .x_axis(
             Axis::default()
                 .title("X Axis")
-                .style(Style::default().fg(Color::Gray))
+                .style(Style::default().fg(theme.axis))
                 .labels(vec![])
-                .bounds([0.0, 20.0]),
+                .bounds([0.0, app.max_capacity_queue as f64]),
         )
         .y_axis(
             Axis::default()
                 .title("Y Axis")
-                .style(Style::default().fg(Color::Gray))
+                .style(Style::default().fg(theme.axis))
                 .labels(vec![])
                 .bounds([-20.0, 20.0]),
         );
@@ -190,20 +1128,18 @@ where
         .margin(1)
         .split(area);
 
-    let block = Block::default()
-        .borders(Borders::ALL)
-        .title(" Network Usage ");
+    let block = bordered(&app.theme).title(" Network Usage ");
     f.render_widget(block, area);
 
     let sparkline = Sparkline::default()
         .block(Block::default().title("Total RX"))
-        .style(Style::default().fg(Color::Cyan))
+        .style(Style::default().fg(app.theme.rx))
         .data(app.network.rx_queue.vec());
     f.render_widget(sparkline, chunks[0]);
 
     let sparkline = Sparkline::default()
         .block(Block::default().title("Total TX"))
-        .style(Style::default().fg(Color::Blue))
+        .style(Style::default().fg(app.theme.tx))
         .data(app.network.tx_queue.vec());
     f.render_widget(sparkline, chunks[1]);
 }
@@ -212,32 +1148,76 @@ fn draw_process_section<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
 where
     B: Backend,
 {
-    let rows = app.process.process_list.iter().map(|s| {
-        Row::new(vec![
-            s.0.to_string(),
-            s.1.to_string(),
-            s.2.to_string(),
-            s.3.to_string(),
-        ])
-        .style(Style::default().fg(Color::Cyan))
+    let search = &app.process.search;
+    let theme = &app.theme;
+    let columns = &app.process.visible_columns();
+    let rows = app.process.rows.iter().map(|r| {
+        let tagged = r.pids().iter().any(|pid| app.process.tagged.contains(pid));
+        let lifecycle = &app.process.lifecycle;
+        let style = if tagged {
+            theme.tagged_style()
+        } else if r.exited {
+            Style::default()
+                .fg(theme.dim)
+                .add_modifier(Modifier::DIM | Modifier::CROSSED_OUT)
+        } else if r.group.is_none() && lifecycle.is_new(r.info.pid) {
+            Style::default().fg(theme.good).add_modifier(Modifier::BOLD)
+        } else if r.group.is_none() && app.process.is_pinned(&r.info) {
+            theme.warn_style()
+        } else if r.matched {
+            Style::default().fg(theme.text)
+        } else {
+            theme.dim_style()
+        };
+        let cells: Vec<Cell> = columns
+            .iter()
+            .map(|column| match column {
+                Column::Name => {
+                    Cell::from(highlight_matches(theme, &r.prefix, &r.info.name, search))
+                }
+                column => Cell::from(column.text(r)),
+            })
+            .collect();
+        Row::new(cells).style(style)
     });
+
+    let arrow = match app.process.sort_order {
+        SortOrder::Ascending => "▲",
+        SortOrder::Descending => "▼",
+    };
+    let grouped = app.process.group_by != GroupBy::Off;
+    let threads = app.process.threads != ThreadMode::Off;
+    let header: Vec<String> = columns
+        .iter()
+        .map(|column| {
+            let title = match column {
+                Column::Pid if grouped => "Count",
+                Column::Pid if threads => "TID",
+                column => column.title(),
+            };
+            if column.sort_by().as_ref() == Some(&app.process.sort_by) {
+                format!("{}{}", title, arrow)
+            } else {
+                title.to_string()
+            }
+        })
+        .collect();
+
+    let total: u32 = columns.iter().map(|c| c.weight()).sum();
+    let widths: Vec<Constraint> = columns
+        .iter()
+        .map(|c| Constraint::Ratio(c.weight(), total.max(1)))
+        .collect();
+
     let table = Table::new(rows)
         .header(
-            match app.process.sort_by {
-                SortBy::CPU => Row::new(vec!["Pid", "Process", "CPU^", "Mem"]),
-                SortBy::MEMORY => Row::new(vec!["Pid", "Process", "CPU", "Mem^"]),
-            }
-            .style(Style::default().fg(Color::Yellow))
-            .bottom_margin(1),
+            Row::new(header)
+                .style(theme.header_style())
+                .bottom_margin(1),
         )
-        .block(Block::default().title(" Processes ").borders(Borders::ALL))
-        .highlight_style(Style::default().fg(Color::LightRed))
-        .widths(&[
-            Constraint::Percentage(10),
-            Constraint::Percentage(70),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-        ]);
+        .block(bordered(theme).title(process_title(app)))
+        .highlight_style(theme.selected_style())
+        .widths(&widths);
 
     let mut state = TableState::default();
     state.select(Some(app.process.active_index));
@@ -245,15 +1225,79 @@ where
     f.render_stateful_widget(table, area, &mut state);
 }
 
+fn process_title(app: &App) -> String {
+    let mut title = match app.process.threads {
+        ThreadMode::Off => String::from(" Processes "),
+        ThreadMode::Process(pid) => format!(" Threads of {} ", pid),
+        ThreadMode::All => String::from(" Threads "),
+    };
+    if app.process.group_by != GroupBy::Off {
+        title += &format!("(by {}) ", app.process.group_by.label());
+    } else if app.process.tree_view {
+        title += "(tree) ";
+    }
+
+    if !app.process.tagged.is_empty() {
+        title += &format!("[{} tagged] ", app.process.tagged.len());
+    }
+    if !app.process.pins.is_empty() {
+        title += &format!("[{} pinned] ", app.process.pins.len());
+    }
+
+    let search = &app.process.search;
+    if app.input_mode == InputMode::Search {
+        title += &format!("{}_ ", search.describe());
+    } else if search.is_active() {
+        title += &format!("[{}] ", search.describe());
+    }
+
+    title
+}
+
+/// Splits the name cell so the parts matching the search stand out.
+fn highlight_matches<'a>(
+    theme: &Theme,
+    prefix: &'a str,
+    name: &'a str,
+    search: &Search,
+) -> Spans<'a> {
+    let mut spans = vec![Span::raw(prefix)];
+    let mut last = 0;
+    for (start, end) in search.match_ranges(name) {
+        if start < last {
+            continue;
+        }
+        spans.push(Span::raw(&name[last..start]));
+        spans.push(Span::styled(
+            &name[start..end],
+            Style::default()
+                .fg(theme.warn)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+        ));
+        last = end;
+    }
+    spans.push(Span::raw(&name[last..]));
+
+    Spans::from(spans)
+}
+
 fn draw_third_row<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
 where
     B: Backend,
 {
-    let chunks = Layout::default()
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .direction(Direction::Horizontal)
-        .split(area);
+    let chunks = split_shown(
+        area,
+        Direction::Horizontal,
+        &[
+            app.settings.shows(Widget::Net),
+            app.settings.shows(Widget::Proc),
+        ],
+    );
 
-    draw_network_section(f, app, chunks[0]);
-    draw_process_section(f, app, chunks[1]);
+    if let Some(area) = chunks[0] {
+        draw_network_section(f, app, area);
+    }
+    if let Some(area) = chunks[1] {
+        draw_process_section(f, app, area);
+    }
 }
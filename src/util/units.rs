@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Deserialize;
+
+/// Whether byte counts are shown in powers of 1000 rather than 1024. Set
+/// once from the settings at startup.
+static DECIMAL: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ByteUnits {
+    Binary,
+    Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Temperature {
+    Celsius,
+    Fahrenheit,
+}
+
+pub fn set_byte_units(units: ByteUnits) {
+    DECIMAL.store(units == ByteUnits::Decimal, Ordering::Relaxed);
+}
+
+/// Formats a byte count, e.g. `512B`, `1.5K`, `23.4M`. The units are binary
+/// unless decimal ones were picked in the settings.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "K", "M", "G", "T", "P"];
+
+    let base = if DECIMAL.load(Ordering::Relaxed) {
+        1000.0
+    } else {
+        1024.0
+    };
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= base && unit + 1 < UNITS.len() {
+        value /= base;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{}{}", bytes, UNITS[0])
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
+
+/// Formats a duration in seconds as e.g. `3d 4h`, `2h 5m` or `42s`.
+pub fn format_duration(secs: u64) -> String {
+    let (days, hours, mins) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, mins)
+    } else if mins > 0 {
+        format!("{}m {}s", mins, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Formats seconds since the epoch as local `YYYY-MM-DD HH:MM:SS`.
+pub fn format_timestamp(secs: u64) -> String {
+    let time = secs as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return secs.to_string();
+    }
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
+
+/// Formats a sensor reading given in Celsius, e.g. `45.0°C`.
+pub fn format_temperature(celsius: f32, unit: Temperature) -> String {
+    match unit {
+        Temperature::Celsius => format!("{:.1}°C", celsius),
+        Temperature::Fahrenheit => format!("{:.1}°F", celsius * 9.0 / 5.0 + 32.0),
+    }
+}
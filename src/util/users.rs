@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+use std::fs;
+
+/// Maps uids to login names using `/etc/passwd`. Accounts that only exist
+/// in other NSS sources are shown by number instead.
+pub fn read_passwd() -> HashMap<u32, String> {
+    let mut users = HashMap::new();
+
+    if let Ok(contents) = fs::read_to_string("/etc/passwd") {
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 3 {
+                continue;
+            }
+            if let Ok(uid) = fields[2].parse::<u32>() {
+                users.entry(uid).or_insert_with(|| fields[0].to_string());
+            }
+        }
+    }
+
+    users
+}
+
+pub fn user_name(users: &HashMap<u32, String>, uid: u32) -> String {
+    match users.get(&uid) {
+        Some(name) => name.clone(),
+        None => uid.to_string(),
+    }
+}